@@ -0,0 +1,96 @@
+#!/usr/bin/env python3
+# Writes MAPHEAD.TST and GAMEMAPS.TST, a tiny Wolfenstein 3D map archive
+# used by the importer tests. The planes are RLEW then Carmack compressed
+# exactly like the original data files.
+import struct
+
+RLEW_TAG = 0xABCD
+NEAR_TAG = 0xA7
+FAR_TAG = 0xA8
+
+WALLS = [
+    "11111111",
+    "1......1",
+    "1..3...1",
+    "1......1",
+    "1......L",
+    "11111111",
+]
+
+# 19 is the player start facing north, 23 a static object,
+# 108 an enemy and 98 a push wall marker
+OBJECTS = {(2, 1): 19, (5, 2): 23, (5, 4): 108, (7, 3): 98}
+
+
+def wall_value(c):
+    return {"1": 1, "3": 3, ".": 108, "L": 21}[c]
+
+
+def rlew_compress(words):
+    out = [len(words) * 2]
+    i = 0
+    while i < len(words):
+        run = 1
+        while i + run < len(words) and words[i + run] == words[i] and run < 0xFFFF:
+            run += 1
+        if run > 3 or words[i] == RLEW_TAG:
+            out += [RLEW_TAG, run, words[i]]
+        else:
+            out += [words[i]] * run
+        i += run
+    return out
+
+
+def carmack_compress(words):
+    out = bytearray(struct.pack("<H", len(words) * 2))
+    i = 0
+    while i < len(words):
+        best_len, best_offset = 0, 0
+        for offset in range(1, min(i, 255) + 1):
+            length = 0
+            while i + length < len(words) and length < 255 and words[i + length] == words[i - offset + length]:
+                length += 1
+            if length > best_len:
+                best_len, best_offset = length, offset
+        if best_len >= 2:
+            out += struct.pack("<HB", (NEAR_TAG << 8) | best_len, best_offset)
+            i += best_len
+            continue
+        word = words[i]
+        if word >> 8 in (NEAR_TAG, FAR_TAG):
+            out += struct.pack("<HB", word & 0xFF00, word & 0xFF)
+        else:
+            out += struct.pack("<H", word)
+        i += 1
+    return bytes(out)
+
+
+def plane(words):
+    return carmack_compress(rlew_compress(words))
+
+
+def main():
+    width, height = len(WALLS[0]), len(WALLS)
+    walls = [wall_value(c) for row in WALLS for c in row]
+    objects = [OBJECTS.get((x, y), 0) for y in range(height) for x in range(width)]
+    planes = [plane(walls), plane(objects), plane([0] * (width * height))]
+
+    gamemaps = bytearray(b"TED5v1.0")
+    offsets = []
+    for p in planes:
+        offsets.append(len(gamemaps))
+        gamemaps += p
+    header_offset = len(gamemaps)
+    gamemaps += struct.pack("<3i3H2H16s", *offsets, *(len(p) for p in planes), width, height, b"Test Room")
+    gamemaps += b"!ID!"
+
+    maphead = struct.pack("<H", RLEW_TAG) + struct.pack("<100i", header_offset, *([0] * 99))
+
+    with open("MAPHEAD.TST", "wb") as f:
+        f.write(maphead)
+    with open("GAMEMAPS.TST", "wb") as f:
+        f.write(gamemaps)
+
+
+if __name__ == "__main__":
+    main()
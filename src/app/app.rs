@@ -4,13 +4,24 @@ use sdl2::{
 };
 
 use crate::{
-    colorbuffer::colorbuffer::ColorBuffer, game::game::Game, player::player::Player,
+    campaign::campaign::Campaign,
+    colorbuffer::colorbuffer::ColorBuffer,
+    game::game::Game,
+    player::player::{Player, WalkDirection},
+    sprite::sprite::Sprite,
+    texture::{manager::TextureManager, surfaces::SurfaceTextures},
     timekeeper::timekeeper::TimeKeeper,
+    utils::time::format_time,
+    window::window::{Resolution, TILE_SIZE},
 };
 
 pub struct App<'a> {
     pub game: Game,
     pub player: Player,
+    pub textures: TextureManager,
+    pub surfaces: SurfaceTextures,
+    pub sprites: Vec<Sprite>,
+    pub resolution: Resolution,
     pub sdl_context: &'a sdl2::Sdl,
     pub video_subsystem: &'a sdl2::VideoSubsystem,
     pub texture_creator: &'a TextureCreator<WindowContext>,
@@ -23,17 +34,28 @@ pub struct App<'a> {
 
 impl<'a> App<'a> {
     pub fn new(
+        game: Game,
+        mut textures: TextureManager,
+        resolution: Resolution,
         sdl_context: &'a sdl2::Sdl,
         video_subsystem: &'a sdl2::VideoSubsystem,
         canvas: &'a mut Canvas<sdl2::video::Window>,
         texture_creator: &'a TextureCreator<WindowContext>,
     ) -> Result<Self, String> {
         let event_pump = sdl_context.event_pump()?;
-        let color_buffer = ColorBuffer::new(&texture_creator)?;
+        Self::set_title(canvas, &game)?;
+        let color_buffer = ColorBuffer::new(&texture_creator, resolution)?;
+        let surfaces = Self::surface_textures(&game, &mut textures);
+        let sprites = Self::sprites(&game, &mut textures);
+        let player = Player::new(game.spawn.x, game.spawn.y, game.spawn.angle);
 
         Ok(App {
-            game: Game::default(),
-            player: Player::default(),
+            game,
+            player,
+            textures,
+            surfaces,
+            sprites,
+            resolution,
             sdl_context,
             video_subsystem,
             canvas,
@@ -44,4 +66,103 @@ impl<'a> App<'a> {
             is_running: true,
         })
     }
+
+    fn set_title(canvas: &mut Canvas<sdl2::video::Window>, game: &Game) -> Result<(), String> {
+        canvas
+            .window_mut()
+            .set_title(&game.title())
+            .map_err(|err| format!("failed to set window title: {}", err))
+    }
+
+    fn surface_textures(game: &Game, textures: &mut TextureManager) -> SurfaceTextures {
+        let (surfaces, warnings) = SurfaceTextures::new(
+            &game.tiles,
+            &game.floor,
+            &game.ceiling,
+            game.sky.as_ref(),
+            textures,
+        );
+        for warning in warnings {
+            eprintln!("{}: warning: {}", game.name, warning);
+        }
+        surfaces
+    }
+
+    fn sprites(game: &Game, textures: &mut TextureManager) -> Vec<Sprite> {
+        let (sprites, warnings) = Sprite::from_entities(&game.entities, &game.actors, textures);
+        for warning in warnings {
+            eprintln!("{}: warning: {}", game.name, warning);
+        }
+        sprites
+    }
+
+    // exit_reached tells whether the player stands on an exit
+    // or walks into a solid one such as a switch
+    pub fn exit_reached(&self) -> bool {
+        let reach = TILE_SIZE as f64 / 2.0;
+        let ahead_x = self.player.x + self.player.rotation_angle.cos() * reach;
+        let ahead_y = self.player.y + self.player.rotation_angle.sin() * reach;
+        let pushing = matches!(self.player.walk_direction, WalkDirection::Forward)
+            && self.game.is_coordinate_solid(ahead_x, ahead_y)
+            && self.game.is_exit(ahead_x, ahead_y);
+        pushing || self.game.is_exit(self.player.x, self.player.y)
+    }
+
+    // use_ahead opens or closes the first door within a tile ahead of the player,
+    // or pushes the first secret wall there
+    pub fn use_ahead(&mut self) {
+        for reach in [0.5, 1.0] {
+            let distance = reach * TILE_SIZE as f64;
+            let ahead_x = self.player.x + self.player.rotation_angle.cos() * distance;
+            let ahead_y = self.player.y + self.player.rotation_angle.sin() * distance;
+            if self.game.use_door(ahead_x, ahead_y)
+                || self
+                    .game
+                    .push_secret(ahead_x, ahead_y, self.player.x, self.player.y)
+            {
+                return;
+            }
+        }
+    }
+
+    // finish_level reports the time spent on the current level and
+    // swaps in the next level of the campaign, the window and renderer are kept.
+    // After the last level the app stops.
+    pub fn finish_level(&mut self, campaign: &mut Campaign) -> Result<(), String> {
+        let (found, secrets) = (self.game.secrets_found(), self.game.secrets);
+        let time = self.player.stats.finish_level(found, secrets);
+        println!(
+            "{} finished in {}, {} of {} secret(s) found",
+            self.game.name,
+            format_time(time),
+            found,
+            secrets
+        );
+
+        match campaign.advance() {
+            Some(level) => {
+                self.game = Game::from_level(
+                    level.clone(),
+                    self.game.tiles.clone(),
+                    self.game.actors.clone(),
+                );
+                self.surfaces = Self::surface_textures(&self.game, &mut self.textures);
+                self.sprites = Self::sprites(&self.game, &mut self.textures);
+                self.player.respawn(&self.game.spawn);
+                Self::set_title(self.canvas, &self.game)?;
+            }
+            None => {
+                println!(
+                    "{} completed, {} level(s) in {}, {} of {} secret(s) found",
+                    campaign.name,
+                    self.player.stats.levels_completed,
+                    format_time(self.player.stats.total_time),
+                    self.player.stats.secrets_found,
+                    self.player.stats.secrets
+                );
+                self.is_running = false;
+            }
+        }
+        Ok(())
+    }
 }
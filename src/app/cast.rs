@@ -1,184 +1,528 @@
 use crate::{
-    ray::ray::Ray,
+    game::game::{Game, PORTAL_NUDGE},
+    ray::{
+        ray::{Leg, Ray},
+        view::View,
+    },
+    tile::tile::{SegmentHit, TileId},
     utils::geometry::distance_between_points,
-    window::window::{DISTANCE_PROJ_PLANE, NUM_RAYS, TILE_SIZE, WINDOW_HEIGHT, WINDOW_WIDTH},
+    window::window::TILE_SIZE,
 };
 
 use super::app::App;
 
-#[derive(Default)]
-pub struct VerticalIntersectionResult {
-    pub vert_x_wall_collision: f64,
-    pub vert_y_wall_collision: f64,
-    pub vert_wall_content: i32,
-    pub found_vert_collision: bool,
+// How many legs a ray goes on for past the first one, through portals or off mirrors,
+// before the portal or mirror ending the last leg is drawn as a wall
+const MAX_PORTALS: u32 = 8;
+// How many of those legs may start with a bounce off a mirror
+const MAX_BOUNCES: u32 = 4;
+
+// Intersection is a wall a ray meets while crossing the grid lines of one direction,
+// segments of the tile being met somewhere inside it
+#[derive(Debug, Clone, Copy)]
+pub struct Intersection {
+    pub x: f64,
+    pub y: f64,
+    pub content: TileId,
+    pub segment: Option<SegmentHit>,
 }
 
-#[derive(Default)]
-pub struct HorizontalIntersectionResult {
-    pub horz_x_wall_collision: f64,
-    pub horz_y_wall_collision: f64,
-    pub horz_wall_content: i32,
-    pub found_horz_collision: bool,
+// Sight is what is left to see along a ray: the eye it is seen from, how far
+// the ray has travelled through portals and mirrors and how far along it walls
+// can still rise above the ones met, in distances from the eye
+#[derive(Debug, Clone, Copy)]
+pub struct Sight {
+    pub eye_height: f64,
+    pub travelled: f64,
+    pub limit: f64,
 }
 
-impl<'a> App<'a> {
-    pub fn calculate_horizontal_intersection(
-        &mut self,
-        ray: &mut Ray,
-    ) -> HorizontalIntersectionResult {
-        let mut result = HorizontalIntersectionResult::default();
-
-        let mut y_intersection = (self.player.y / TILE_SIZE as f64).floor() * TILE_SIZE as f64;
-        if ray.is_facing_down {
-            y_intersection += TILE_SIZE as f64;
-        }
+impl Sight {
+    // meet lowers the limit past a wall of tile met at distance from where
+    // the ray set off after travelling
+    pub fn meet(&mut self, game: &Game, tile: TileId, distance: f64) {
+        let distance = self.travelled + distance;
+        self.limit = self
+            .limit
+            .min(game.sight_limit(tile, distance, self.eye_height));
+    }
+}
 
-        let x_intersection = self.player.x + ((y_intersection - self.player.y) / ray.angle.tan());
+// calculate_horizontal_intersection returns the walls met from origin crossing
+// horizontal grid lines, nearest first, until no wall further could be seen
+pub fn calculate_horizontal_intersection(
+    game: &Game,
+    ray: &Ray,
+    origin: (f64, f64),
+    mut sight: Sight,
+) -> Vec<Intersection> {
+    let mut hits = Vec::new();
+
+    let mut y_intersection = (origin.1 / TILE_SIZE as f64).floor() * TILE_SIZE as f64;
+    if ray.is_facing_down {
+        y_intersection += TILE_SIZE as f64;
+    }
+
+    let x_intersection = origin.0 + ((y_intersection - origin.1) / ray.angle.tan());
+
+    let mut y_step = TILE_SIZE as f64;
+    if ray.is_facing_up {
+        y_step *= -1.0;
+    }
 
-        let mut y_step = TILE_SIZE as f64;
+    let mut x_step = TILE_SIZE as f64 / ray.angle.tan();
+    if ray.is_facing_left && x_step > 0.0 {
+        x_step *= -1.0;
+    }
+    if ray.is_facing_right && x_step < 0.0 {
+        x_step *= -1.0;
+    }
+
+    let mut next_horz_x_collision = x_intersection;
+    let mut next_horz_y_collision = y_intersection;
+
+    let world_width = game.world_width();
+    let world_height = game.world_height();
+
+    while next_horz_x_collision >= 0.0
+        && next_horz_x_collision <= world_width
+        && next_horz_y_collision >= 0.0
+        && next_horz_y_collision <= world_height
+    {
+        // nothing this far can rise above the walls met so far
+        let crossed = distance_between_points(
+            origin.0,
+            origin.1,
+            next_horz_x_collision,
+            next_horz_y_collision,
+        );
+        if sight.travelled + crossed > sight.limit {
+            break;
+        }
+        let x_to_check = next_horz_x_collision;
+        let mut y_to_check = next_horz_y_collision;
         if ray.is_facing_up {
-            y_step *= -1.0;
+            y_to_check -= 1.0;
+        }
+        let content = game.tile_at(x_to_check, y_to_check).unwrap_or_default();
+        let hit = if let Some(door) = game.door_at(x_to_check, y_to_check) {
+            // horizontal doors are hit half a step further, in the middle of the cell,
+            // unless the ray leaves the cell sideways or passes the open part
+            let door_x = next_horz_x_collision + x_step / 2.0;
+            let door_y = next_horz_y_collision + y_step / 2.0;
+            let offset = door_x - door.x as f64 * TILE_SIZE as f64;
+            (door.horizontal
+                && (0.0..TILE_SIZE as f64).contains(&offset)
+                && offset >= door.opening * TILE_SIZE as f64)
+                .then_some(Intersection {
+                    x: door_x,
+                    y: door_y,
+                    content,
+                    segment: None,
+                })
+        } else if let Some(segment) =
+            game.segment_hit(x_to_check, y_to_check, origin.0, origin.1, ray.angle)
+        {
+            // tiles with segments are hit where the ray meets a segment inside them
+            Some(Intersection {
+                x: segment.x,
+                y: segment.y,
+                content,
+                segment: Some(segment),
+            })
+        } else {
+            // raised floors are met like low walls, their tops showing
+            (game.blocks_sight(x_to_check, y_to_check)
+                || game.floor_height(x_to_check, y_to_check) > 0.0)
+                .then_some(Intersection {
+                    x: next_horz_x_collision,
+                    y: next_horz_y_collision,
+                    content,
+                    segment: None,
+                })
+        };
+        if let Some(hit) = hit {
+            let distance = distance_between_points(origin.0, origin.1, hit.x, hit.y);
+            sight.meet(game, content, distance);
+            hits.push(hit);
         }
+        next_horz_x_collision += x_step;
+        next_horz_y_collision += y_step;
+    }
+    hits
+}
+
+// calculate_vertical_intersection does the same crossing vertical grid lines
+pub fn calculate_vertical_intersection(
+    game: &Game,
+    ray: &Ray,
+    origin: (f64, f64),
+    mut sight: Sight,
+) -> Vec<Intersection> {
+    let mut hits = Vec::new();
+
+    let mut x_intersection = (origin.0 / TILE_SIZE as f64).floor() * TILE_SIZE as f64;
+    if ray.is_facing_right {
+        x_intersection += TILE_SIZE as f64;
+    }
 
-        let mut x_step = TILE_SIZE as f64 / ray.angle.tan();
-        if ray.is_facing_left && x_step > 0.0 {
-            x_step *= -1.0;
+    let y_intersection = origin.1 + ((x_intersection - origin.0) * ray.angle.tan());
+
+    let mut x_step = TILE_SIZE as f64;
+    if ray.is_facing_left {
+        x_step *= -1.0;
+    }
+
+    let mut y_step = TILE_SIZE as f64 * ray.angle.tan();
+    if ray.is_facing_up && y_step > 0.0 {
+        y_step *= -1.0;
+    }
+    if ray.is_facing_down && y_step < 0.0 {
+        y_step *= -1.0;
+    }
+
+    let mut next_vert_x_collision = x_intersection;
+    let mut next_vert_y_collision = y_intersection;
+
+    let world_width = game.world_width();
+    let world_height = game.world_height();
+
+    while next_vert_x_collision >= 0.0
+        && next_vert_x_collision <= world_width
+        && next_vert_y_collision >= 0.0
+        && next_vert_y_collision <= world_height
+    {
+        // nothing this far can rise above the walls met so far
+        let crossed = distance_between_points(
+            origin.0,
+            origin.1,
+            next_vert_x_collision,
+            next_vert_y_collision,
+        );
+        if sight.travelled + crossed > sight.limit {
+            break;
         }
-        if ray.is_facing_right && x_step < 0.0 {
-            x_step *= -1.0;
+        let mut x_to_check = next_vert_x_collision;
+        if ray.is_facing_left {
+            x_to_check -= 1.0;
         }
 
-        let mut next_horz_x_collision = x_intersection;
-        let mut next_horz_y_collision = y_intersection;
-
-        while next_horz_x_collision >= 0.0
-            && next_horz_x_collision <= WINDOW_WIDTH as f64
-            && next_horz_y_collision >= 0.0
-            && next_horz_y_collision <= WINDOW_HEIGHT as f64
+        let y_to_check = next_vert_y_collision;
+        let content = game.tile_at(x_to_check, y_to_check).unwrap_or_default();
+        let hit = if let Some(door) = game.door_at(x_to_check, y_to_check) {
+            // vertical doors are hit in the middle of the cell like horizontal ones
+            let door_x = next_vert_x_collision + x_step / 2.0;
+            let door_y = next_vert_y_collision + y_step / 2.0;
+            let offset = door_y - door.y as f64 * TILE_SIZE as f64;
+            (!door.horizontal
+                && (0.0..TILE_SIZE as f64).contains(&offset)
+                && offset >= door.opening * TILE_SIZE as f64)
+                .then_some(Intersection {
+                    x: door_x,
+                    y: door_y,
+                    content,
+                    segment: None,
+                })
+        } else if let Some(segment) =
+            game.segment_hit(x_to_check, y_to_check, origin.0, origin.1, ray.angle)
         {
-            let x_to_check = next_horz_x_collision;
-            let mut y_to_check = next_horz_y_collision;
-            if ray.is_facing_up {
-                y_to_check -= 1.0;
-            }
-            if self.game.is_coordinate_solid(x_to_check, y_to_check) {
-                result.horz_x_wall_collision = next_horz_x_collision;
-                result.horz_y_wall_collision = next_horz_y_collision;
-                result.horz_wall_content = self.game.game_map
-                    [(y_to_check / TILE_SIZE as f64).floor() as usize]
-                    [(x_to_check / TILE_SIZE as f64).floor() as usize];
-                result.found_horz_collision = true;
-                break;
-            } else {
-                next_horz_x_collision += x_step;
-                next_horz_y_collision += y_step;
-            }
+            Some(Intersection {
+                x: segment.x,
+                y: segment.y,
+                content,
+                segment: Some(segment),
+            })
+        } else {
+            (game.blocks_sight(x_to_check, y_to_check)
+                || game.floor_height(x_to_check, y_to_check) > 0.0)
+                .then_some(Intersection {
+                    x: next_vert_x_collision,
+                    y: next_vert_y_collision,
+                    content,
+                    segment: None,
+                })
+        };
+        if let Some(hit) = hit {
+            let distance = distance_between_points(origin.0, origin.1, hit.x, hit.y);
+            sight.meet(game, content, distance);
+            hits.push(hit);
         }
-        result
+        next_vert_x_collision += x_step;
+        next_vert_y_collision += y_step;
     }
+    hits
+}
 
-    pub fn calculate_vertical_intersection(&mut self, ray: &mut Ray) -> VerticalIntersectionResult {
-        let mut result = VerticalIntersectionResult::default();
+impl<'a> App<'a> {
+    // wall_ray turns a wall met by the ray from origin into a hit of its own
+    fn wall_ray(&self, ray: Ray, hit: Intersection, is_vertical: bool, origin: (f64, f64)) -> Ray {
+        let mut wall = ray;
+        wall.distance = distance_between_points(origin.0, origin.1, hit.x, hit.y);
+        wall.x_collision = hit.x;
+        wall.y_collision = hit.y;
+        wall.content = hit.content;
+        wall.is_vertical_collision = is_vertical;
+        wall.base = self.floor_before(hit.x, hit.y, ray.angle);
 
-        let mut x_intersection = (self.player.x / TILE_SIZE as f64).floor() * TILE_SIZE as f64;
-        if ray.is_facing_right {
-            x_intersection += TILE_SIZE as f64;
+        // the texture follows the panel of a door as it slides open,
+        // segments are textured by the distance along them
+        let along = match is_vertical {
+            true => hit.y,
+            false => hit.x,
+        };
+        let slide = match self.game.tiles.is_door(hit.content) {
+            true => self
+                .game
+                .door_at(hit.x, hit.y)
+                .map_or(0.0, |door| door.opening * TILE_SIZE as f64),
+            false => 0.0,
+        };
+        wall.texture_offset = along.rem_euclid(TILE_SIZE as f64) - slide;
+        if let Some(segment) = hit.segment {
+            wall.is_vertical_collision = segment.is_vertical;
+            wall.texture_offset = segment.along.rem_euclid(TILE_SIZE as f64);
         }
+        // mirrors and portal tiles joined to another one let the ray go on,
+        // the view of what it meets next is found by cast_ray
+        let next = match self.game.tiles.is_mirror(hit.content) {
+            true => Some(
+                self.game
+                    .reflect(hit.x, hit.y, ray.angle, wall.is_vertical_collision),
+            ),
+            false if self.game.tiles.is_portal(hit.content) => {
+                self.game.warp(hit.x, hit.y, ray.angle)
+            }
+            false => None,
+        };
+        wall.next = next.map(|(x, y, angle)| Leg {
+            x,
+            y,
+            angle,
+            view: 0,
+        });
+        wall
+    }
+
+    // floor_before is the floor height just short of x, y along angle,
+    // where the foot of a wall met there is seen
+    fn floor_before(&self, x: f64, y: f64, angle: f64) -> f64 {
+        self.game.floor_height(
+            x - angle.cos() * PORTAL_NUDGE,
+            y - angle.sin() * PORTAL_NUDGE,
+        )
+    }
 
-        let y_intersection = self.player.y + ((x_intersection - self.player.x) * ray.angle.tan());
+    // cast_from finds the walls seen from origin along angle, nearest first.
+    // Rays go on past walls that something taller behind can rise above,
+    // up to the first portal or mirror, lowering the limit of sight past them.
+    fn cast_from(&mut self, mut angle: f64, origin: (f64, f64), sight: &mut Sight) -> Vec<Ray> {
+        let ray = Ray::new(&mut angle);
+        let mut walls = Vec::new();
 
-        let mut x_step = TILE_SIZE as f64;
-        if ray.is_facing_left {
-            x_step *= 1.0;
+        // the cell of the origin is not crossed into, its segments are checked apart
+        if let Some(segment) = self
+            .game
+            .segment_hit(origin.0, origin.1, origin.0, origin.1, ray.angle)
+        {
+            let hit = Intersection {
+                x: segment.x,
+                y: segment.y,
+                content: self.game.tile_at(origin.0, origin.1).unwrap_or_default(),
+                segment: Some(segment),
+            };
+            walls.push(self.wall_ray(ray, hit, segment.is_vertical, origin));
         }
-
-        let mut y_step = TILE_SIZE as f64 * ray.angle.tan();
-        if ray.is_facing_up && y_step > 0.0 {
-            y_step *= -1.0;
+        for hit in calculate_horizontal_intersection(&self.game, &ray, origin, *sight) {
+            walls.push(self.wall_ray(ray, hit, false, origin));
         }
-        if ray.is_facing_down && y_step < 0.0 {
-            y_step *= -1.0;
+        for hit in calculate_vertical_intersection(&self.game, &ray, origin, *sight) {
+            walls.push(self.wall_ray(ray, hit, true, origin));
         }
 
-        let mut next_vert_x_collision = x_intersection;
-        let mut next_vert_y_collision = y_intersection;
+        // walls being pushed are off the grid, they are hit as boxes
+        // wherever they are between two cells
+        for wall in self.game.push_walls.iter().filter(|wall| wall.moving) {
+            let Some((distance, is_vertical)) = wall.intersect(origin.0, origin.1, ray.angle)
+            else {
+                continue;
+            };
+            let (left, top) = wall.origin();
+            let mut hit = ray;
+            hit.distance = distance;
+            hit.x_collision = origin.0 + ray.angle.cos() * distance;
+            hit.y_collision = origin.1 + ray.angle.sin() * distance;
+            hit.content = wall.tile;
+            hit.is_vertical_collision = is_vertical;
+            hit.base = self.floor_before(hit.x_collision, hit.y_collision, ray.angle);
+            hit.texture_offset = match is_vertical {
+                true => hit.y_collision - top,
+                false => hit.x_collision - left,
+            };
+            walls.push(hit);
+        }
 
-        while next_vert_x_collision >= 0.0
-            && next_vert_x_collision <= WINDOW_WIDTH as f64
-            && next_vert_y_collision >= 0.0
-            && next_vert_y_collision <= WINDOW_HEIGHT as f64
-        {
-            let mut x_to_check = next_vert_x_collision;
-            if ray.is_facing_left {
-                x_to_check -= 1.0;
+        // both crossings stop where nothing can be seen any more over the walls
+        // they met, the walls past the limit of both or past a portal are dropped
+        walls.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        let mut seen = Vec::new();
+        for wall in walls {
+            if sight.travelled + wall.distance > sight.limit {
+                break;
             }
-
-            let y_to_check = next_vert_y_collision;
-            if self.game.is_coordinate_solid(x_to_check, y_to_check) {
-                result.vert_x_wall_collision = next_vert_x_collision;
-                result.vert_y_wall_collision = next_vert_y_collision;
-                result.vert_wall_content = self.game.game_map
-                    [(y_to_check / TILE_SIZE as f64) as usize]
-                    [(x_to_check / TILE_SIZE as f64) as usize];
-                result.found_vert_collision = true;
+            // portals and mirrors show what is past them rather than hiding it
+            let last = wall.next.is_some();
+            if !last {
+                sight.meet(&self.game, wall.content, wall.distance);
+            }
+            seen.push(wall);
+            if last {
                 break;
-            } else {
-                next_vert_x_collision += x_step;
-                next_vert_y_collision += y_step;
             }
         }
-        result
+        seen
     }
 
-    pub fn cast_ray(&mut self, mut angle: f64, ray_id: i32) {
-        self.game.rays[ray_id as usize] = Ray::new(&mut angle);
-        let mut ray = self.game.rays[ray_id as usize];
+    // cast_ray finds the walls seen along angle for the column ray_id, nearest
+    // first. A ray reaching a portal goes on from the joined one and a ray
+    // reaching a mirror bounces off it, its distance adding up, until it has
+    // gone on MAX_PORTALS times in all or bounced MAX_BOUNCES times.
+    // Everything met past them is seen through a view of its own.
+    pub fn cast_ray(&mut self, column_angle: f64, ray_id: i32) {
+        let mut walls = Vec::new();
+        let (mut origin, mut angle) = ((self.player.x, self.player.y), column_angle);
+        let (mut view, mut mirrored, mut bounces) = (0, false, 0);
+        let mut sight = Sight {
+            eye_height: self.player.eye_height(),
+            travelled: 0.0,
+            limit: f64::INFINITY,
+        };
+        for legs in 0..=MAX_PORTALS {
+            let mut hits = self.cast_from(angle, origin, &mut sight);
+            let Some(last) = hits.last_mut() else {
+                break;
+            };
+            let mirror = self.game.tiles.is_mirror(last.content);
+            // the last portal or mirror the ray may reach is drawn as a wall
+            if legs == MAX_PORTALS || (mirror && bounces == MAX_BOUNCES) {
+                last.next = None;
+            }
+            if let Some(next) = &mut last.next {
+                // past it the world is seen where the straight ray of the column runs
+                let along = sight.travelled + last.distance + PORTAL_NUDGE;
+                let apparent = (
+                    self.player.x + column_angle.cos() * along,
+                    self.player.y + column_angle.sin() * along,
+                );
+                mirrored ^= mirror;
+                next.view = self.game.view_id(View::new(
+                    next.x,
+                    next.y,
+                    next.angle,
+                    apparent,
+                    column_angle,
+                    mirrored,
+                ));
+            }
+            let last = *last;
+            walls.extend(hits.into_iter().map(|mut hit| {
+                hit.distance += sight.travelled;
+                hit.view = view;
+                hit
+            }));
+            let Some(next) = last.next else {
+                break;
+            };
+            sight.travelled += last.distance + PORTAL_NUDGE;
+            origin = (next.x, next.y);
+            angle = next.angle;
+            view = next.view;
+            bounces += mirror as u32;
+        }
+        self.game.rays[ray_id as usize] = walls;
+    }
 
-        let h = self.calculate_horizontal_intersection(&mut ray);
-        let v = self.calculate_vertical_intersection(&mut ray);
+    // column_angle is the angle of the ray cast for a screen column
+    pub fn column_angle(&self, col: u32) -> f64 {
+        let num_rays = self.resolution.num_rays();
+        self.player.rotation_angle
+            + ((col as f64 - num_rays as f64 / 2.0) / self.resolution.distance_proj_plane()).atan()
+    }
 
-        let mut horz_collision_dist = f64::MAX;
-        let mut vert_collision_dist = f64::MAX;
+    pub fn cast_rays(&mut self) {
+        let num_rays = self.resolution.num_rays();
+        self.game.rays.resize(num_rays as usize, Vec::new());
+        self.game.views = vec![View::IDENTITY];
 
-        if h.found_horz_collision {
-            horz_collision_dist = distance_between_points(
-                self.player.x,
-                self.player.y,
-                h.horz_x_wall_collision,
-                h.horz_y_wall_collision,
-            );
+        for col in 0..num_rays {
+            self.cast_ray(self.column_angle(col), col as i32);
         }
+    }
+}
 
-        if v.found_vert_collision {
-            vert_collision_dist = distance_between_points(
-                self.player.x,
-                self.player.y,
-                v.vert_x_wall_collision,
-                v.vert_y_wall_collision,
-            );
-        }
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
 
-        if vert_collision_dist < horz_collision_dist {
-            self.game.rays[ray_id as usize].distance = vert_collision_dist;
-            self.game.rays[ray_id as usize].x_collision = v.vert_x_wall_collision;
-            self.game.rays[ray_id as usize].y_collision = v.vert_y_wall_collision;
-            self.game.rays[ray_id as usize].content = v.vert_wall_content;
-            self.game.rays[ray_id as usize].is_vertical_collision = true;
-        } else {
-            self.game.rays[ray_id as usize].distance = horz_collision_dist;
-            self.game.rays[ray_id as usize].x_collision = h.horz_x_wall_collision;
-            self.game.rays[ray_id as usize].y_collision = h.horz_y_wall_collision;
-            self.game.rays[ray_id as usize].content = h.horz_wall_content;
-            self.game.rays[ray_id as usize].is_vertical_collision = false;
-        }
+    use super::*;
+    use crate::{level::level::Level, sprite::actor::ActorRegistry, tile::registry::TileRegistry};
+
+    // vertical_walls casts from the cell column x, row y of the game
+    // with the eye eye_height storeys up
+    fn vertical_walls(
+        game: &Game,
+        x: f64,
+        y: f64,
+        mut angle: f64,
+        eye_height: f64,
+    ) -> Vec<Intersection> {
+        let tile = TILE_SIZE as f64;
+        let ray = Ray::new(&mut angle);
+        let sight = Sight {
+            eye_height: eye_height * tile,
+            travelled: 0.0,
+            limit: f64::INFINITY,
+        };
+        calculate_vertical_intersection(game, &ray, (x * tile, y * tile), sight)
     }
 
-    pub fn cast_rays(&mut self) {
-        for col in 0..NUM_RAYS {
-            let angle = self.player.rotation_angle
-                + ((col as f64 - NUM_RAYS as f64 / 2.0) / *DISTANCE_PROJ_PLANE as f64).atan();
-            self.cast_ray(angle, col as i32);
-        }
+    fn first_vertical_wall(x: f64, y: f64, angle: f64) -> Intersection {
+        vertical_walls(&Game::default(), x, y, angle, 0.5)[0]
+    }
+
+    #[test]
+    fn test_west_facing_rays_meet_walls_to_the_west() {
+        let tile = TILE_SIZE as f64;
+        // the west wall of the default map, not the pillar to the east in row 1
+        let hit = first_vertical_wall(5.5, 1.5, PI);
+        assert_eq!(hit.x, tile);
+        assert!((hit.y - 1.5 * tile).abs() < 1e-9);
+        assert_eq!(hit.content, TileId(1));
+
+        // slanting down to the west by half a tile per tile
+        let hit = first_vertical_wall(5.5, 1.5, PI - 0.5f64.atan());
+        assert_eq!(hit.x, tile);
+        assert!((hit.y - 3.75 * tile).abs() < 1e-9);
+        let distance = distance_between_points(5.5 * tile, 1.5 * tile, hit.x, hit.y);
+        assert!((distance - 4.5f64.hypot(2.25) * tile).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rays_stop_where_nothing_rises_above_the_walls_met() {
+        let tile = TILE_SIZE as f64;
+        let tiles = TileRegistry::default();
+        let map = "TTTTTTTT\nT..1.1.T\nTTTTTTTT\n";
+        let level = Level::from_map_source(String::from("towers"), map, &tiles).unwrap();
+        let game = Game::from_level(level, tiles, ActorRegistry::default());
+
+        // towers twice as tall as the walls show over them up to three times
+        // as far from an eye half a storey up, the tower at the end is too far
+        let walls = vertical_walls(&game, 1.5, 1.5, 0.0, 0.5);
+        let xs: Vec<f64> = walls.iter().map(|wall| wall.x / tile).collect();
+        assert_eq!(xs, vec![3.0, 5.0]);
+
+        // from higher up the tower is seen over both walls
+        let walls = vertical_walls(&game, 1.5, 1.5, 0.0, 0.75);
+        let xs: Vec<f64> = walls.iter().map(|wall| wall.x / tile).collect();
+        assert_eq!(xs, vec![3.0, 5.0, 7.0]);
     }
 }
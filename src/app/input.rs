@@ -3,6 +3,9 @@ use sdl2::{event::Event, keyboard::Keycode};
 
 impl<'a> App<'a> {
     pub fn process_input(&mut self) {
+        // doors and secrets are used once the events are read,
+        // the event pump borrows the app
+        let mut use_ahead = false;
         for event in self.event_pump.poll_iter() {
             match event {
                 Event::Quit { .. } => self.is_running = false,
@@ -15,6 +18,12 @@ impl<'a> App<'a> {
                     Keycode::Down => self.player.set_walk_direction("backward").unwrap(),
                     Keycode::Right => self.player.set_turn_direction("right").unwrap(),
                     Keycode::Left => self.player.set_turn_direction("left").unwrap(),
+                    Keycode::PageUp => self.player.set_look_direction("up").unwrap(),
+                    Keycode::PageDown => self.player.set_look_direction("down").unwrap(),
+                    Keycode::Home => self.player.center_view(),
+                    Keycode::LCtrl => self.player.jump(),
+                    Keycode::C => self.player.crouching = true,
+                    Keycode::Space => use_ahead = true,
                     _ => {}
                 },
                 Event::KeyUp {
@@ -27,10 +36,17 @@ impl<'a> App<'a> {
                     Keycode::Right | Keycode::Left => {
                         self.player.set_turn_direction("neutral").unwrap()
                     }
+                    Keycode::PageUp | Keycode::PageDown => {
+                        self.player.set_look_direction("neutral").unwrap()
+                    }
+                    Keycode::C => self.player.crouching = false,
                     _ => {}
                 },
                 _ => {}
             }
         }
+        if use_ahead {
+            self.use_ahead();
+        }
     }
 }
@@ -1,59 +1,380 @@
+use std::{f64::consts::TAU, ops::Range};
+
 use sdl2::{
     pixels::Color,
     rect::{Point, Rect},
 };
 
-use crate::window::window::{
-    DISTANCE_PROJ_PLANE, MINIMAP_SCALING, NUM_COLS, NUM_RAYS, NUM_ROWS, TILE_SIZE, WINDOW_HEIGHT,
-    WINDOW_WIDTH,
+use crate::{
+    game::game::PORTAL_NUDGE,
+    ray::ray::Ray,
+    sprite::sprite::project_sprites,
+    texture::manager::{TextureId, TextureKind},
+    tile::tile::Face,
+    utils::color::{is_transparent, mix_color, shade_color},
+    window::window::{MINIMAP_SCALING, TILE_SIZE},
 };
 
 use super::app::App;
 
+// Layers tell which views each pixel is seen through. Every column passes
+// a list of views, the world as it is first, each one starting at the
+// distance of the portal or mirror leading to it, and every pixel
+// is seen through the views of its column up to its level.
+struct Layers {
+    levels: Vec<u8>,
+    columns: Vec<Vec<(usize, f64)>>,
+}
+
+impl Layers {
+    // level is how many portals and mirrors column x passes before the view
+    fn level(&self, x: usize, view: usize) -> Option<usize> {
+        self.columns[x].iter().position(|(id, _)| *id == view)
+    }
+
+    // shows tells whether something of the view at level of column x
+    // that is distance away can be seen in the pixel at index, between
+    // the portal or mirror leading to the view and the next one
+    fn shows(&self, level: usize, x: usize, index: usize, distance: f64) -> bool {
+        let column = &self.columns[x];
+        let deepest = self.levels[index] as usize;
+        level <= deepest
+            && distance > column[level].1
+            && (level == deepest || distance < column[level + 1].1)
+    }
+}
+
 impl<'a> App<'a> {
     fn generate_3d_projection(&mut self) {
-        for x in 0..NUM_RAYS {
-            if let Some(ray) = self.game.rays.get(x as usize) {
-                let distance = ray.distance;
-                let ray_angle = ray.angle;
-                let player_angle = self.player.rotation_angle;
-                let perp_dist = distance * ((ray_angle - player_angle).cos());
-                let proj_wall_height =
-                    (TILE_SIZE as f64 / perp_dist) * (*DISTANCE_PROJ_PLANE as f64);
-                let wall_segment_height = proj_wall_height as i32;
-
-                let mut top_wall_pixel: i32 =
-                    (WINDOW_HEIGHT as i32 / 2) - (wall_segment_height / 2);
-                if top_wall_pixel < 0 {
-                    top_wall_pixel = 0;
-                }
+        let width = self.resolution.width;
+        let height = self.resolution.height as i32;
+        // the distance of what is drawn in each pixel, sprites behind it are hidden
+        let mut depth = vec![f64::INFINITY; (width * height as u32) as usize];
+        let mut layers = Layers {
+            levels: vec![0; depth.len()],
+            columns: Vec::new(),
+        };
 
-                let mut bottom_wall_pixel: i32 =
-                    (WINDOW_HEIGHT as i32 / 2) + (wall_segment_height / 2);
-                if bottom_wall_pixel > WINDOW_HEIGHT as i32 {
-                    bottom_wall_pixel = WINDOW_HEIGHT as i32;
+        for x in 0..self.resolution.num_rays() {
+            let angle = self.column_angle(x);
+            let origin = (self.player.x, self.player.y);
+            self.draw_floor_and_ceiling(x, 0..height, origin, angle, 0.0);
+
+            // the faces of portals and mirrors show the floor and ceiling
+            // beyond them, walls seen through them are drawn over them
+            let walls = self.game.rays.get(x as usize).cloned().unwrap_or_default();
+            let mut column = vec![(0, 0.0)];
+            for ray in walls.iter() {
+                let Some(next) = ray.next else {
+                    continue;
+                };
+                let rows = self.wall_rows(x, ray);
+                self.draw_floor_and_ceiling(
+                    x,
+                    rows.clone(),
+                    (next.x, next.y),
+                    next.angle,
+                    ray.distance + PORTAL_NUDGE,
+                );
+                for y in rows {
+                    layers.levels[(width * y as u32 + x) as usize] = column.len() as u8;
                 }
+                column.push((next.view, self.perp_distance(x, ray)));
+            }
+            layers.columns.push(column);
+
+            // walls are drawn from the farthest to the nearest,
+            // so that nearer walls cover the ones they stand in front of
+            for ray in walls.iter().rev() {
+                self.draw_wall(x, ray, &mut depth);
+            }
+        }
 
-                // ceiling
-                for y in 0..top_wall_pixel {
-                    let index = (WINDOW_WIDTH * y as u32 + x) as usize;
-                    self.color_buffer.buffer[index] = 0xFF444444;
+        self.draw_sprites(&depth, &layers);
+    }
+
+    // horizon is the row straight ahead of the eye, moved up or down by the pitch
+    fn horizon(&self) -> f64 {
+        self.resolution.height as f64 / 2.0
+            + self.player.pitch.tan() * self.resolution.distance_proj_plane()
+    }
+
+    // draw_floor_and_ceiling fills the rows of column x, the walls are drawn over them.
+    // Every row is projected back onto the floor below the eye or the ceiling
+    // one storey up, the distance grows as rows near the horizon. The ray leaves
+    // origin along ray_angle, having travelled so far through portals. The floor
+    // is as high as the one at origin as far as that reaches, ground level past
+    // it, where raised floors are drawn over it like low walls.
+    fn draw_floor_and_ceiling(
+        &mut self,
+        x: u32,
+        rows: Range<i32>,
+        origin: (f64, f64),
+        ray_angle: f64,
+        travelled: f64,
+    ) {
+        let width = self.resolution.width;
+        let height = self.resolution.height as i32;
+        let distance_proj_plane = self.resolution.distance_proj_plane();
+        let (ray_cos, ray_sin) = (ray_angle.cos(), ray_angle.sin());
+        let correction = (self.column_angle(x) - self.player.rotation_angle).cos();
+        let eye_height = self.player.eye_height();
+        let horizon = self.horizon();
+        let origin_floor = self.game.floor_height(origin.0, origin.1);
+        // project finds the perpendicular distance and the point of a row
+        // on a plane that far from the eye
+        let project = |row: f64, plane: f64| {
+            let perp_distance = plane * distance_proj_plane / row;
+            let distance = perp_distance / correction - travelled;
+            (
+                perp_distance,
+                origin.0 + ray_cos * distance,
+                origin.1 + ray_sin * distance,
+            )
+        };
+
+        for y in rows {
+            let is_floor = y as f64 + 0.5 >= horizon;
+            let row = (y as f64 + 0.5 - horizon).abs();
+            let plane = match is_floor {
+                true => eye_height - origin_floor,
+                false => TILE_SIZE as f64 - eye_height,
+            };
+            let (mut perp_distance, mut world_x, mut world_y) = project(row, plane);
+            if is_floor && self.game.floor_height(world_x, world_y) != origin_floor {
+                (perp_distance, world_x, world_y) = project(row, eye_height);
+            }
+
+            let tile = self.game.tile_at(world_x, world_y);
+            let index = (width * y as u32 + x) as usize;
+
+            // open ceilings show the sky panorama, which turns with the ray angle
+            // and spans half a screen up from the horizon
+            if let Some(id) = self.surfaces.sky(tile).filter(|_| !is_floor) {
+                let sky = self.textures.get(id);
+                let u = ray_angle.rem_euclid(TAU) / TAU;
+                let v = 1.0 - (horizon - y as f64) / (height as f64 / 2.0);
+                self.color_buffer.buffer[index] = sky.texel(
+                    (u * sky.width as f64) as u32,
+                    ((v.max(0.0) * sky.height as f64) as u32).min(sky.height - 1),
+                );
+                continue;
+            }
+
+            let (texture, color) = match is_floor {
+                true => (self.surfaces.floor(tile), self.game.floor.color),
+                false => (self.surfaces.ceiling(tile), self.game.ceiling.color),
+            };
+            self.color_buffer.buffer[index] = self.game.apply_fog(
+                self.surface_color(texture, color, world_x, world_y),
+                perp_distance,
+            );
+        }
+    }
+
+    // surface_color is the color of a floor or ceiling texture at a world coordinate
+    fn surface_color(&self, texture: Option<TextureId>, color: u32, x: f64, y: f64) -> u32 {
+        match texture {
+            Some(id) => {
+                let texture = self.textures.get(id);
+                let u = x.rem_euclid(TILE_SIZE as f64) / TILE_SIZE as f64;
+                let v = y.rem_euclid(TILE_SIZE as f64) / TILE_SIZE as f64;
+                texture.texel(
+                    (u * texture.width as f64) as u32,
+                    (v * texture.height as f64) as u32,
+                )
+            }
+            None => color,
+        }
+    }
+
+    // perp_distance is the distance of a wall met by the ray of column x
+    // along the view direction, rays keep their column through portals
+    fn perp_distance(&self, x: u32, ray: &Ray) -> f64 {
+        ray.distance * (self.column_angle(x) - self.player.rotation_angle).cos()
+    }
+
+    // wall_rows are the rows of column x a wall met by the ray covers,
+    // from the floor in front of it up to the top of its tile. Raised floors
+    // met from higher ones cover none, the start is past the end.
+    fn wall_rows(&self, x: u32, ray: &Ray) -> Range<i32> {
+        let height = self.resolution.height as i32;
+        let horizon = self.horizon();
+        let eye_height = self.player.eye_height();
+        let scale = self.resolution.distance_proj_plane() / self.perp_distance(x, ray);
+        let wall_height = self.game.tiles.top(ray.content) * TILE_SIZE as f64;
+        let top = horizon - (wall_height - eye_height) * scale;
+        let bottom = horizon + (eye_height - ray.base) * scale;
+        (top.max(0.0) as i32).min(height)..(bottom.ceil() as i32).clamp(0, height)
+    }
+
+    // draw_wall draws a wall met by the ray of column x from the floor
+    // up to the height of its tile, recording its distance in depth.
+    // Portals and mirrors leave what is seen through them, mirrors tint it.
+    fn draw_wall(&mut self, x: u32, ray: &Ray, depth: &mut [f64]) {
+        let width = self.resolution.width;
+        let height = self.resolution.height as i32;
+        let horizon = self.horizon();
+        let distance_proj_plane = self.resolution.distance_proj_plane();
+        let eye_height = self.player.eye_height();
+        let perp_dist = self.perp_distance(x, ray);
+        let wall_height = self.game.tiles.top(ray.content) * TILE_SIZE as f64;
+
+        // rows and heights above the floor are tied by the distance of the wall
+        let height_at =
+            |y: i32| eye_height - (y as f64 + 0.5 - horizon) * perp_dist / distance_proj_plane;
+        let rows = self.wall_rows(x, ray);
+        let (top_wall_pixel, bottom_wall_pixel) = (rows.start, rows.end);
+        if ray.next.is_some() {
+            if let Some(tint) = self.game.tiles.tint(ray.content) {
+                for y in rows {
+                    let index = (width * y as u32 + x) as usize;
+                    let color = self.color_buffer.buffer[index];
+                    self.color_buffer.buffer[index] =
+                        mix_color(color, tint, (tint >> 24) as f64 / 255.0);
                 }
+            }
+            return;
+        }
+
+        let face = ray.face();
+        let texture = self
+            .game
+            .tiles
+            .get(ray.content)
+            .and_then(|def| def.textures.get(face))
+            .map(|name| self.textures.get(self.textures.id(TextureKind::Wall, name)));
+        let shade = if ray.is_vertical_collision { 1.0 } else { 0.8 };
 
-                // wall
-                for y in top_wall_pixel..bottom_wall_pixel {
-                    let index = (WINDOW_WIDTH * y as u32 + x) as usize;
-                    if self.game.rays[x as usize].is_vertical_collision {
-                        self.color_buffer.buffer[index] = 0xFFFFFFFF;
-                    } else {
-                        self.color_buffer.buffer[index] = 0xFFCCCCCC;
+        // the texture column is where the ray hit along the wall face,
+        // mirrored on the faces seen with the coordinates decreasing
+        let texture_x = texture.map(|texture| {
+            let offset = ray.texture_offset / TILE_SIZE as f64;
+            let texture_x = ((offset * texture.width as f64) as u32).min(texture.width - 1);
+            match face {
+                Face::East | Face::North => texture.width - 1 - texture_x,
+                _ => texture_x,
+            }
+        });
+        let flat_color = shade_color(self.game.tiles.minimap_color(ray.content), shade);
+
+        for y in top_wall_pixel..bottom_wall_pixel {
+            // the texture repeats once per storey, from the floor up
+            let color = match (texture, texture_x) {
+                (Some(texture), Some(texture_x)) => {
+                    let v = 1.0 - (height_at(y) / TILE_SIZE as f64).fract().abs();
+                    let texel = texture.texel(
+                        texture_x,
+                        ((v * texture.height as f64) as u32).min(texture.height - 1),
+                    );
+                    // clear texels leave what is behind the wall and the depth
+                    // of it, so that sprites behind can show through
+                    if is_transparent(texel) {
+                        continue;
                     }
+                    shade_color(texel, shade)
                 }
+                _ => flat_color,
+            };
+            let index = (width * y as u32 + x) as usize;
+            self.color_buffer.buffer[index] = self.game.apply_fog(color, perp_dist);
+            depth[index] = perp_dist;
+        }
+
+        // the top of a block or raised floor lower than the eye is seen,
+        // reaching back over the rows that still land on the same tile
+        let is_block = !self.game.tiles.is_door(ray.content)
+            && !self.game.tiles.is_see_through(ray.content)
+            && self.game.tiles.segments(ray.content).is_empty();
+        if !is_block || wall_height >= eye_height {
+            return;
+        }
+        let correction = (self.column_angle(x) - self.player.rotation_angle).cos();
+        let texture = self.surfaces.floor(Some(ray.content));
+        let first = top_wall_pixel.min(bottom_wall_pixel).min(height);
+        for y in (0..first).rev() {
+            let row = y as f64 + 0.5 - horizon;
+            if row <= 0.0 {
+                break;
+            }
+            // the top is found from the hit, which works past portals too
+            let perp_distance = (eye_height - wall_height) * distance_proj_plane / row;
+            let distance = perp_distance / correction - ray.distance;
+            let world_x = ray.x_collision + ray.angle.cos() * distance;
+            let world_y = ray.y_collision + ray.angle.sin() * distance;
+            if self.game.tile_at(world_x, world_y) != Some(ray.content) {
+                break;
+            }
+            let color = self.surface_color(texture, self.game.floor.color, world_x, world_y);
+            let index = (width * y as u32 + x) as usize;
+            self.color_buffer.buffer[index] = self.game.apply_fog(color, perp_distance);
+            depth[index] = perp_distance;
+        }
+    }
+
+    // draw_sprites draws the sprites of every view, each one where its view is
+    // seen. Sprites seen through a view are projected from where the eye appears
+    // in the world, mirrored views show them the other way around.
+    fn draw_sprites(&mut self, depth: &[f64], layers: &Layers) {
+        let width = self.resolution.width as i32;
+        let height = self.resolution.height as i32;
+        let mut projections = Vec::new();
+        for (id, view) in self.game.views.iter().enumerate() {
+            let (x, y) = view.unapply(self.player.x, self.player.y);
+            let angle = view.unapply_angle(self.player.rotation_angle);
+            for mut projection in project_sprites(&self.sprites, x, y, angle, &self.resolution) {
+                if view.mirrored {
+                    projection.center_x = width as f64 - projection.center_x;
+                }
+                projections.push((id, view.mirrored, projection));
+            }
+        }
+        projections.sort_by(|a, b| b.2.depth.total_cmp(&a.2.depth));
 
-                // floor
-                for y in bottom_wall_pixel..WINDOW_HEIGHT as i32 {
-                    let index = (WINDOW_WIDTH * y as u32 + x) as usize;
-                    self.color_buffer.buffer[index] = 0xFF777777;
+        for (view, mirrored, projection) in projections {
+            let texture = self.textures.get(projection.texture);
+            // sprite sheets are strips of square frames
+            let frame_width = texture.height.min(texture.width);
+            let frame_x = projection.frame * frame_width;
+            let size = projection.size;
+            let left = projection.center_x - size / 2.0;
+            // sprites stand on the floor under them and are one storey high
+            let base = self.game.floor_height(projection.x, projection.y);
+            let top = self.horizon()
+                - size * (TILE_SIZE as f64 + base - self.player.eye_height()) / TILE_SIZE as f64;
+            let (first_x, last_x) = (
+                left.max(0.0) as i32,
+                ((left + size).ceil() as i32).min(width),
+            );
+            let (first_y, last_y) = (
+                top.max(0.0) as i32,
+                ((top + size).ceil() as i32).min(height),
+            );
+
+            for x in first_x..last_x {
+                let Some(level) = layers.level(x as usize, view) else {
+                    continue;
+                };
+                let column = (((x as f64 + 0.5 - left) / size * frame_width as f64) as u32)
+                    .min(frame_width - 1);
+                let texture_x = frame_x
+                    + match mirrored {
+                        true => frame_width - 1 - column,
+                        false => column,
+                    };
+                for y in first_y..last_y {
+                    let texture_y = (((y as f64 + 0.5 - top) / size * texture.height as f64)
+                        as u32)
+                        .min(texture.height - 1);
+                    let index = (width * y + x) as usize;
+                    let texel = texture.texel(texture_x, texture_y);
+                    if is_transparent(texel)
+                        || depth[index] <= projection.depth
+                        || !layers.shows(level, x as usize, index, projection.depth)
+                    {
+                        continue;
+                    }
+                    self.color_buffer.buffer[index] = self.game.apply_fog(texel, projection.depth);
                 }
             }
         }
@@ -69,7 +390,7 @@ impl<'a> App<'a> {
 
         self.color_buffer
             .texture
-            .update(None, color_bytes, WINDOW_WIDTH as usize * 4)
+            .update(None, color_bytes, self.color_buffer.width as usize * 4)
             .map_err(|err| format!("Error updating texture: {:?}", err))?;
 
         self.canvas
@@ -84,23 +405,42 @@ impl<'a> App<'a> {
         self.canvas.fill_rect(Rect::new(
             0,
             0,
-            (MINIMAP_SCALING * NUM_COLS as f64 * TILE_SIZE as f64) as u32,
-            (MINIMAP_SCALING * NUM_ROWS as f64 * TILE_SIZE as f64) as u32,
+            (MINIMAP_SCALING * self.game.world_width()) as u32,
+            (MINIMAP_SCALING * self.game.world_height()) as u32,
         ))?;
 
-        for i in 0..NUM_ROWS {
-            for j in 0..NUM_COLS {
-                let x_tile = j * TILE_SIZE;
-                let y_tile = i * TILE_SIZE;
+        for (i, row) in self.game.game_map.rows().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                let x_tile = j as u32 * TILE_SIZE;
+                let y_tile = i as u32 * TILE_SIZE;
 
-                let tile_color = if self.game.game_map[i as usize][j as usize] != 0 {
-                    255
-                } else {
-                    0
-                };
+                let tile_color = self.game.tiles.minimap_color(*tile);
+
+                self.canvas.set_draw_color(Color::RGB(
+                    (tile_color >> 16) as u8,
+                    (tile_color >> 8) as u8,
+                    tile_color as u8,
+                ));
+
+                // tiles with segments show their segments only
+                let segments = self.game.tiles.segments(*tile);
+                if !segments.is_empty() {
+                    for segment in segments {
+                        let (a, b) = segment.world(j, i);
+                        self.canvas.draw_line(
+                            Point::new(
+                                (a.0 * MINIMAP_SCALING) as i32,
+                                (a.1 * MINIMAP_SCALING) as i32,
+                            ),
+                            Point::new(
+                                (b.0 * MINIMAP_SCALING) as i32,
+                                (b.1 * MINIMAP_SCALING) as i32,
+                            ),
+                        )?;
+                    }
+                    continue;
+                }
 
-                self.canvas
-                    .set_draw_color(Color::RGBA(tile_color, tile_color, tile_color, 255));
                 let map_tile = Rect::new(
                     (x_tile as f64 * MINIMAP_SCALING) as i32,
                     (y_tile as f64 * MINIMAP_SCALING) as i32,
@@ -118,14 +458,18 @@ impl<'a> App<'a> {
     fn render_rays(&mut self) -> Result<(), String> {
         self.canvas.set_draw_color(Color::RGBA(255, 0, 0, 255));
 
-        for i in 0..NUM_RAYS {
+        // each ray is drawn up to the farthest wall it sees, or the first portal or mirror
+        for walls in self.game.rays.iter() {
+            let Some(ray) = walls.iter().find(|ray| ray.next.is_some()).or(walls.last()) else {
+                continue;
+            };
             let ray_start = Point::new(
                 (MINIMAP_SCALING * self.player.x) as i32,
                 (MINIMAP_SCALING * self.player.y) as i32,
             );
             let ray_end = Point::new(
-                (MINIMAP_SCALING * self.game.rays[i as usize].x_collision) as i32,
-                (MINIMAP_SCALING * self.game.rays[i as usize].y_collision) as i32,
+                (MINIMAP_SCALING * ray.x_collision) as i32,
+                (MINIMAP_SCALING * ray.y_collision) as i32,
             );
             self.canvas.draw_line(ray_start, ray_end)?;
         }
@@ -133,6 +477,22 @@ impl<'a> App<'a> {
         Ok(())
     }
 
+    fn render_entities(&mut self) -> Result<(), String> {
+        self.canvas.set_draw_color(Color::RGBA(255, 255, 0, 255));
+
+        for entity in self.game.entities.iter() {
+            let marker = Rect::new(
+                (MINIMAP_SCALING * entity.x) as i32 - 1,
+                (MINIMAP_SCALING * entity.y) as i32 - 1,
+                3,
+                3,
+            );
+            self.canvas.fill_rect(marker)?;
+        }
+
+        Ok(())
+    }
+
     fn render_player(&mut self) -> Result<(), String> {
         self.canvas.set_draw_color(Color::RGBA(255, 255, 255, 255));
 
@@ -170,6 +530,7 @@ impl<'a> App<'a> {
         self.color_buffer.clear(Color::RGBA(0, 0, 0, 255))?;
 
         self.render_map()?;
+        self.render_entities()?;
         self.render_rays()?;
         self.render_player()?;
         self.canvas.present();
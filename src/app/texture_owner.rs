@@ -4,7 +4,10 @@ use sdl2::{
     VideoSubsystem,
 };
 
-use crate::window::window::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::{
+    game::game::Game, options::options::Options, texture::manager::TextureManager,
+    window::window::Resolution,
+};
 
 use super::app::App;
 
@@ -16,10 +19,11 @@ pub struct TextureOwner {
 }
 
 impl TextureOwner {
-    pub fn new() -> Result<Self, String> {
+    pub fn new(options: &Options) -> Result<Self, String> {
         let sdl_context = Self::build_context()?;
         let video_subsystem = Self::build_video_subsystem(&sdl_context)?;
-        let canvas = Self::build_canvas(&video_subsystem)?;
+        let (width, height) = options.window_size();
+        let canvas = Self::build_canvas(&video_subsystem, width, height)?;
         let texture_creator = Self::build_texture_creator(&canvas);
         Ok(Self {
             sdl_context,
@@ -29,8 +33,16 @@ impl TextureOwner {
         })
     }
 
-    pub fn build_app<'b>(&'b mut self) -> Result<App<'b>, String> {
+    pub fn build_app<'b>(
+        &'b mut self,
+        game: Game,
+        textures: TextureManager,
+        resolution: Resolution,
+    ) -> Result<App<'b>, String> {
         let app = App::new(
+            game,
+            textures,
+            resolution,
             &self.sdl_context,
             &self.video_subsystem,
             &mut self.canvas,
@@ -49,9 +61,11 @@ impl TextureOwner {
 
     fn build_canvas(
         video_subsystem: &VideoSubsystem,
+        width: u32,
+        height: u32,
     ) -> Result<Canvas<sdl2::video::Window>, String> {
         let window = video_subsystem
-            .window("raycasting", WINDOW_WIDTH, WINDOW_HEIGHT)
+            .window("raycasting", width, height)
             .position_centered()
             .borderless()
             .build()
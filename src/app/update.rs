@@ -12,8 +12,17 @@ impl<'a> App<'a> {
 
         self.timekeeper.calculate_delta();
         unsafe { self.timekeeper.set_ticks(sdl2::sys::SDL_GetTicks64()) }
+        self.player.stats.tick(self.timekeeper.delta_time());
+        for sprite in self.sprites.iter_mut() {
+            sprite.advance(self.timekeeper.delta_time());
+        }
+        self.game
+            .update_doors(self.timekeeper.delta_time(), self.player.x, self.player.y);
+        self.game
+            .update_push_walls(self.timekeeper.delta_time(), self.player.x, self.player.y);
         self.player
             .move_player(self.timekeeper.delta_time(), &self.game);
+        self.player.update_camera(self.timekeeper.delta_time());
         self.cast_rays();
     }
 }
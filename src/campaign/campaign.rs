@@ -0,0 +1,144 @@
+use std::{fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    level::{level::Level, loader::load_level},
+    tile::registry::TileRegistry,
+};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCampaign {
+    name: String,
+    #[serde(rename = "level")]
+    levels: Vec<RawEntry>,
+}
+
+// Level paths are relative to the campaign file,
+// map_number picks the map of a Wolfenstein 3D archive
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawEntry {
+    path: String,
+    #[serde(default)]
+    map_number: usize,
+}
+
+// Campaign is the ordered list of levels played in one run.
+// Every level is loaded and validated up front so that a broken
+// level is reported on start rather than halfway through.
+#[derive(Debug, Clone)]
+pub struct Campaign {
+    pub name: String,
+    levels: Vec<Level>,
+    current: usize,
+}
+
+impl Campaign {
+    // single wraps a lone level, finishing it ends the run
+    pub fn single(level: Level) -> Self {
+        Campaign {
+            name: level.name.clone(),
+            levels: vec![level],
+            current: 0,
+        }
+    }
+
+    pub fn from_file(path: &str, tiles: &TileRegistry) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read campaign {}: {}", path, err))?;
+        let base_dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        Self::from_toml(&source, base_dir, tiles).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    pub fn from_toml(source: &str, base_dir: &Path, tiles: &TileRegistry) -> Result<Self, String> {
+        let raw: RawCampaign = toml::from_str(source).map_err(|err| err.to_string())?;
+        if raw.levels.is_empty() {
+            return Err(String::from("campaign has no levels"));
+        }
+
+        let count = raw.levels.len();
+        let mut levels = Vec::with_capacity(count);
+        for (index, entry) in raw.levels.into_iter().enumerate() {
+            let path = base_dir.join(&entry.path);
+            let level = load_level(Some(&path.to_string_lossy()), entry.map_number, tiles)?;
+            // only the last level may be a dead end
+            if index + 1 < count && !level.has_exit(tiles) {
+                return Err(format!("level {} ({}) has no exit", index + 1, entry.path));
+            }
+            levels.push(level);
+        }
+
+        Ok(Campaign {
+            name: raw.name,
+            levels,
+            current: 0,
+        })
+    }
+
+    pub fn current(&self) -> &Level {
+        &self.levels[self.current]
+    }
+
+    // advance moves on to the next level,
+    // returning None once the last level is finished
+    pub fn advance(&mut self) -> Option<&Level> {
+        if self.current + 1 >= self.levels.len() {
+            return None;
+        }
+        self.current += 1;
+        Some(&self.levels[self.current])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_campaign() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/maps/campaign.toml");
+        let mut campaign = Campaign::from_file(path, &TileRegistry::default()).unwrap();
+        assert_eq!(campaign.name, "Sample campaign");
+        assert_eq!(campaign.current().name, "Entrance");
+        assert_eq!(
+            campaign.advance().map(|level| level.name.as_str()),
+            Some("Hall")
+        );
+        assert!(campaign.advance().is_none());
+        assert_eq!(campaign.current().name, "Hall");
+    }
+
+    #[test]
+    fn test_levels_need_exits() {
+        let dir = std::env::temp_dir().join(format!("raycasting_dead_end_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("raycasting_dead_end.map"), "111\n1.1\n111\n").unwrap();
+        let source = r#"
+            name = "Dead end"
+
+            [[level]]
+            path = "raycasting_dead_end.map"
+
+            [[level]]
+            path = "raycasting_dead_end.map"
+        "#;
+        let err = Campaign::from_toml(source, &dir, &TileRegistry::default()).unwrap_err();
+        let empty = Campaign::from_toml(
+            "name = \"Empty\"\nlevel = []\n",
+            &dir,
+            &TileRegistry::default(),
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(err, "level 1 (raycasting_dead_end.map) has no exit");
+        assert!(empty.is_err());
+    }
+
+    #[test]
+    fn test_single() {
+        let mut campaign = Campaign::single(Level::default());
+        assert_eq!(campaign.name, "Entrance");
+        assert!(campaign.advance().is_none());
+    }
+}
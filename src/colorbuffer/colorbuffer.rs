@@ -5,22 +5,27 @@ use sdl2::{
     video::WindowContext,
 };
 
-use crate::window::window::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::window::window::Resolution;
 
 pub struct ColorBuffer<'a> {
     pub buffer: Vec<u32>,
+    pub width: u32,
     pub texture_creator: &'a TextureCreator<WindowContext>,
     pub texture: Texture<'a>,
 }
 
 impl<'a> ColorBuffer<'a> {
-    pub fn new(texture_creator: &'a TextureCreator<WindowContext>) -> Result<Self, String> {
-        let buffer = vec![0; (WINDOW_WIDTH * WINDOW_HEIGHT) as usize];
+    pub fn new(
+        texture_creator: &'a TextureCreator<WindowContext>,
+        resolution: Resolution,
+    ) -> Result<Self, String> {
+        let Resolution { width, height } = resolution;
+        let buffer = vec![0; (width * height) as usize];
 
         let texture = match texture_creator.create_texture_streaming(
             PixelFormatEnum::ARGB8888,
-            WINDOW_WIDTH,
-            WINDOW_HEIGHT,
+            width,
+            height,
         ) {
             Ok(texture) => texture,
             Err(_) => return Err(String::from("Failed to create texture")),
@@ -28,6 +33,7 @@ impl<'a> ColorBuffer<'a> {
 
         Ok(Self {
             buffer,
+            width,
             texture_creator,
             texture,
         })
@@ -47,7 +53,7 @@ impl<'a> ColorBuffer<'a> {
         };
 
         self.texture
-            .update(None, color_bytes, WINDOW_WIDTH as usize * 4)
+            .update(None, color_bytes, self.width as usize * 4)
             .map_err(|e| format!("Error updating texture: {}", e))?;
 
         Ok(())
@@ -0,0 +1,133 @@
+// Seconds a door takes to slide fully open or shut
+pub const SLIDE_TIME: f64 = 1.0;
+// Seconds an open door waits before it closes again
+pub const CLOSE_DELAY: f64 = 4.0;
+// How far a door has to be open for the player to walk through
+pub const PASSABLE_OPENING: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DoorState {
+    Closed,
+    Opening,
+    // open doors count the seconds they have been left open
+    Open(f64),
+    Closing,
+}
+
+// Door is the state of a door tile at the cell x, y. The panel runs across
+// the middle of the cell, along the x axis when the door is horizontal,
+// and slides sideways into the wall by its opening, from 0 shut to 1 open.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Door {
+    pub x: usize,
+    pub y: usize,
+    pub horizontal: bool,
+    pub opening: f64,
+    pub state: DoorState,
+}
+
+impl Door {
+    pub fn new(x: usize, y: usize, horizontal: bool) -> Self {
+        Door {
+            x,
+            y,
+            horizontal,
+            opening: 0.0,
+            state: DoorState::Closed,
+        }
+    }
+
+    // activate opens a door that is shut or closing and closes an open one
+    pub fn activate(&mut self) {
+        self.state = match self.state {
+            DoorState::Closed | DoorState::Closing => DoorState::Opening,
+            DoorState::Opening | DoorState::Open(_) => DoorState::Closing,
+        };
+    }
+
+    // update slides the door by delta_time seconds. A blocked door,
+    // with something standing in its cell, does not close.
+    pub fn update(&mut self, delta_time: f64, blocked: bool) {
+        let step = delta_time / SLIDE_TIME;
+        self.state = match self.state {
+            DoorState::Closed => DoorState::Closed,
+            DoorState::Opening => {
+                self.opening = (self.opening + step).min(1.0);
+                match self.opening >= 1.0 {
+                    true => DoorState::Open(0.0),
+                    false => DoorState::Opening,
+                }
+            }
+            DoorState::Open(_) if blocked => DoorState::Open(0.0),
+            DoorState::Open(time) if time + delta_time >= CLOSE_DELAY => DoorState::Closing,
+            DoorState::Open(time) => DoorState::Open(time + delta_time),
+            DoorState::Closing if blocked => DoorState::Opening,
+            DoorState::Closing => {
+                self.opening = (self.opening - step).max(0.0);
+                match self.opening <= 0.0 {
+                    true => DoorState::Closed,
+                    false => DoorState::Closing,
+                }
+            }
+        };
+    }
+
+    pub fn is_passable(&self) -> bool {
+        self.opening >= PASSABLE_OPENING
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // eighths of a second add up without rounding errors
+    fn run(door: &mut Door, seconds: f64, blocked: bool) {
+        for _ in 0..(seconds * 8.0).round() as usize {
+            door.update(0.125, blocked);
+        }
+    }
+
+    #[test]
+    fn test_door_opens_and_closes_by_itself() {
+        let mut door = Door::new(3, 4, true);
+        run(&mut door, 1.0, false);
+        assert_eq!(door.state, DoorState::Closed);
+
+        door.activate();
+        run(&mut door, SLIDE_TIME / 2.0, false);
+        assert!((door.opening - 0.5).abs() < 1e-9);
+        assert!(!door.is_passable());
+        run(&mut door, SLIDE_TIME / 2.0, false);
+        assert!(matches!(door.state, DoorState::Open(_)));
+        assert!(door.is_passable());
+
+        run(&mut door, CLOSE_DELAY + SLIDE_TIME / 2.0, false);
+        assert_eq!(door.state, DoorState::Closing);
+        run(&mut door, SLIDE_TIME, false);
+        assert_eq!((door.state, door.opening), (DoorState::Closed, 0.0));
+    }
+
+    #[test]
+    fn test_blocked_door_stays_open() {
+        let mut door = Door::new(0, 0, false);
+        door.activate();
+        run(&mut door, SLIDE_TIME + CLOSE_DELAY * 2.0, true);
+        assert!(matches!(door.state, DoorState::Open(_)));
+
+        // a door closing onto something opens again
+        run(&mut door, CLOSE_DELAY + SLIDE_TIME / 2.0, false);
+        assert_eq!(door.state, DoorState::Closing);
+        door.update(0.125, true);
+        assert_eq!(door.state, DoorState::Opening);
+    }
+
+    #[test]
+    fn test_activate_closes_an_open_door() {
+        let mut door = Door::new(0, 0, false);
+        door.activate();
+        run(&mut door, SLIDE_TIME, false);
+        door.activate();
+        assert_eq!(door.state, DoorState::Closing);
+    }
+}
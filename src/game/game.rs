@@ -1,53 +1,401 @@
+use std::{collections::HashMap, f64::consts::PI};
+
 use crate::{
-    ray::ray::Ray,
-    window::window::{NUM_RAYS, TILE_SIZE, WINDOW_HEIGHT, WINDOW_WIDTH},
+    level::{
+        level::{Entity, Fog, Level, Sky, Spawn, Surface, EXIT_ENTITY},
+        portal::{leave_cell, Portal},
+    },
+    map::grid::Grid,
+    ray::{ray::Ray, view::View},
+    sprite::actor::ActorRegistry,
+    tile::{
+        registry::TileRegistry,
+        tile::{SegmentHit, TileId},
+    },
+    utils::{geometry::distance_to_segment, geometry::line_intersection, time::format_time},
+    window::window::TILE_SIZE,
 };
 
+use super::{door::Door, push_wall::PushWall};
+
+// How close the player may come to a segment
+const SEGMENT_CLEARANCE: f64 = 2.0;
+
+// How far past a portal or off a mirror rays and the player go on
+pub const PORTAL_NUDGE: f64 = 0.01;
+
 // Ok
 pub struct Game {
-    pub game_map: [[i32; 20]; 13],
-    pub rays: Vec<Ray>,
+    pub name: String,
+    pub game_map: Grid,
+    pub tiles: TileRegistry,
+    pub actors: ActorRegistry,
+    pub spawn: Spawn,
+    pub ceiling: Surface,
+    pub floor: Surface,
+    pub sky: Option<Sky>,
+    pub fog: Option<Fog>,
+    pub par_time: Option<f64>,
+    pub entities: Vec<Entity>,
+    // doors by cell
+    pub doors: HashMap<(usize, usize), Door>,
+    // walls pushed so far, one per secret found
+    pub push_walls: Vec<PushWall>,
+    pub portals: Vec<Portal>,
+    // number of secret tiles in the level
+    pub secrets: u32,
+    // walls met by the ray of each screen column, nearest first
+    pub rays: Vec<Vec<Ray>>,
+    // views the rays see through, the first one is the world as it is
+    pub views: Vec<View>,
+    // height of the tallest wall in storeys
+    pub max_height: f64,
 }
 
 impl Default for Game {
     fn default() -> Self {
-        let rays: Vec<Ray> = vec![Ray::new(&mut 0.0); NUM_RAYS as usize];
+        Self::from_level(
+            Level::default(),
+            TileRegistry::default(),
+            ActorRegistry::default(),
+        )
+    }
+}
 
+impl Game {
+    pub fn from_level(level: Level, tiles: TileRegistry, actors: ActorRegistry) -> Self {
+        let doors = find_doors(&level.grid, &tiles);
+        let secrets = level
+            .grid
+            .rows()
+            .flatten()
+            .filter(|tile| tiles.is_secret(**tile))
+            .count() as u32;
+        let max_height = level
+            .grid
+            .rows()
+            .flatten()
+            .filter(|tile| tiles.blocks_sight(**tile) || !tiles.segments(**tile).is_empty())
+            .map(|tile| tiles.height(*tile))
+            .fold(1.0, f64::max);
         Game {
-            game_map: Self::initialize_game_map(),
-            rays,
+            name: level.name,
+            game_map: level.grid,
+            tiles,
+            actors,
+            spawn: level.spawn,
+            ceiling: level.ceiling,
+            floor: level.floor,
+            sky: level.sky,
+            fog: level.fog,
+            par_time: level.par_time,
+            entities: level.entities,
+            doors,
+            push_walls: Vec::new(),
+            portals: level.portals,
+            secrets,
+            rays: Vec::new(),
+            views: vec![View::IDENTITY],
+            max_height,
         }
     }
-}
 
-impl Game {
-    fn initialize_game_map() -> [[i32; 20]; 13] {
-        [
-            [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
-            [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1],
-            [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 0, 0, 0, 1],
-            [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-            [1, 0, 0, 0, 2, 2, 0, 3, 0, 4, 0, 5, 0, 6, 0, 0, 0, 0, 0, 1],
-            [1, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-            [1, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-            [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 0, 0, 1],
-            [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5],
-            [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 5],
-            [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 5],
-            [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 5],
-            [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 5, 5, 5, 5, 5, 5],
-        ]
+    // title describes the level for the window title bar
+    pub fn title(&self) -> String {
+        match self.par_time {
+            Some(par) => format!("{} (par {})", self.name, format_time(par)),
+            None => self.name.clone(),
+        }
+    }
+
+    // world_width and world_height are the map extents in world units
+    pub fn world_width(&self) -> f64 {
+        (self.game_map.width() as u32 * TILE_SIZE) as f64
     }
 
+    pub fn world_height(&self) -> f64 {
+        (self.game_map.height() as u32 * TILE_SIZE) as f64
+    }
+
+    // tile_at returns the content of the cell containing the world
+    // coordinate or None when the coordinate lies outside of the map
+    pub fn tile_at(&self, x: f64, y: f64) -> Option<TileId> {
+        let (ind_x, ind_y) = cell_of(x, y)?;
+        self.game_map.get(ind_x, ind_y)
+    }
+
+    // door_at returns the door in the cell containing the world coordinate
+    pub fn door_at(&self, x: f64, y: f64) -> Option<&Door> {
+        self.doors.get(&cell_of(x, y)?)
+    }
+
+    // is_coordinate_solid tells whether the player collides at the coordinate,
+    // everything outside of the map is solid, doors until open enough
+    // and walls being pushed wherever they are
     pub fn is_coordinate_solid(&self, x: f64, y: f64) -> bool {
-        if x < 0.0 || x >= WINDOW_WIDTH as f64 || y < 0.0 || y > WINDOW_HEIGHT as f64 {
+        if let Some(door) = self.door_at(x, y) {
+            return !door.is_passable();
+        }
+        if self.push_walls.iter().any(|wall| wall.contains(x, y)) {
             return true;
         }
-        let ind_x = (x / TILE_SIZE as f64).floor() as usize;
-        let ind_y = (y / TILE_SIZE as f64).floor() as usize;
+        self.tile_at(x, y)
+            .is_none_or(|tile| self.tiles.is_solid(tile))
+    }
+
+    // warp carries a ray or the player reaching x, y along angle through the
+    // portal in the cell ahead. It returns the point just past the joined
+    // portal and the new heading, None when the cell leads nowhere.
+    pub fn warp(&self, x: f64, y: f64, angle: f64) -> Option<(f64, f64, f64)> {
+        let (sin, cos) = angle.sin_cos();
+        let cell = cell_of(x + cos * PORTAL_NUDGE, y + sin * PORTAL_NUDGE)?;
+        let (x, y, angle) = self
+            .portals
+            .iter()
+            .find_map(|portal| portal.warp(cell, x, y, angle))?;
+        let (sin, cos) = angle.sin_cos();
+        Some((x + cos * PORTAL_NUDGE, y + sin * PORTAL_NUDGE, angle))
+    }
+
+    // reflect returns where a ray reaching x, y along angle goes on after
+    // bouncing off a face along a vertical grid line or a horizontal one
+    pub fn reflect(&self, x: f64, y: f64, angle: f64, is_vertical: bool) -> (f64, f64, f64) {
+        let angle = match is_vertical {
+            true => PI - angle,
+            false => -angle,
+        };
+        let (sin, cos) = angle.sin_cos();
+        (x + cos * PORTAL_NUDGE, y + sin * PORTAL_NUDGE, angle)
+    }
+
+    // view_id returns the index of the view, added unless an alike one exists
+    pub fn view_id(&mut self, view: View) -> usize {
+        match self.views.iter().position(|other| other.same(&view)) {
+            Some(id) => id,
+            None => {
+                self.views.push(view);
+                self.views.len() - 1
+            }
+        }
+    }
+
+    // pass_through returns where the player stepping to x, y along angle
+    // comes out of a portal: past the far side of the joined cell
+    pub fn pass_through(&self, x: f64, y: f64, angle: f64) -> Option<(f64, f64, f64)> {
+        let (x, y, angle) = self.warp(x, y, angle)?;
+        let distance = leave_cell(x, y, angle) + PORTAL_NUDGE;
+        Some((
+            x + angle.cos() * distance,
+            y + angle.sin() * distance,
+            angle,
+        ))
+    }
+
+    // segment_hit returns the nearest segment of the cell containing x, y
+    // hit by a ray from from_x, from_y along angle
+    pub fn segment_hit(
+        &self,
+        x: f64,
+        y: f64,
+        from_x: f64,
+        from_y: f64,
+        angle: f64,
+    ) -> Option<SegmentHit> {
+        let (cell_x, cell_y) = cell_of(x, y)?;
+        let tile = self.game_map.get(cell_x, cell_y)?;
+        self.tiles
+            .segments(tile)
+            .iter()
+            .filter_map(|segment| segment.intersect(cell_x, cell_y, from_x, from_y, angle))
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    // hits_segment tells whether moving from x0, y0 to x1, y1 runs through
+    // a segment of the cells at either end or closes in on one too much
+    pub fn hits_segment(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> bool {
+        let cells = [cell_of(x0, y0), cell_of(x1, y1)];
+        cells.iter().flatten().any(|&(cell_x, cell_y)| {
+            let tile = self.game_map.get(cell_x, cell_y).unwrap_or_default();
+            self.tiles.segments(tile).iter().any(|segment| {
+                let (a, b) = segment.world(cell_x, cell_y);
+                let crossed = line_intersection((x0, y0), (x1 - x0, y1 - y0), a, b)
+                    .is_some_and(|(t, _)| (0.0..=1.0).contains(&t));
+                let distance = distance_to_segment((x1, y1), a, b);
+                crossed
+                    || (distance < SEGMENT_CLEARANCE
+                        && distance < distance_to_segment((x0, y0), a, b))
+            })
+        })
+    }
+
+    // use_door opens or closes the door at the coordinate,
+    // telling whether there was one
+    pub fn use_door(&mut self, x: f64, y: f64) -> bool {
+        let door = cell_of(x, y).and_then(|cell| self.doors.get_mut(&cell));
+        door.map(|door| door.activate()).is_some()
+    }
+
+    // update_doors slides the doors by delta_time seconds, doors are kept
+    // from closing on the player at x, y and on the entities
+    pub fn update_doors(&mut self, delta_time: f64, x: f64, y: f64) {
+        let player = cell_of(x, y);
+        for (cell, door) in self.doors.iter_mut() {
+            let blocked = player == Some(*cell)
+                || self
+                    .entities
+                    .iter()
+                    .any(|entity| cell_of(entity.x, entity.y) == Some(*cell));
+            door.update(delta_time, blocked);
+        }
+    }
+
+    // push_secret pushes the secret wall at x, y away from from_x, from_y,
+    // along the axis the wall is mostly seen from. It tells whether the wall
+    // started moving, a wall with no room behind it stays in place.
+    pub fn push_secret(&mut self, x: f64, y: f64, from_x: f64, from_y: f64) -> bool {
+        let Some((cell_x, cell_y)) = cell_of(x, y) else {
+            return false;
+        };
+        let tile = match self.game_map.get(cell_x, cell_y) {
+            Some(tile) if self.tiles.is_secret(tile) => tile,
+            _ => return false,
+        };
+        if self
+            .push_walls
+            .iter()
+            .any(|wall| wall.cell() == Some((cell_x, cell_y)))
+        {
+            return false;
+        }
 
-        self.game_map[ind_y][ind_x] != 0
+        let tile_size = TILE_SIZE as f64;
+        let dx = (cell_x as f64 + 0.5) * tile_size - from_x;
+        let dy = (cell_y as f64 + 0.5) * tile_size - from_y;
+        let wall = match dx.abs() >= dy.abs() {
+            true => PushWall::new(tile, cell_x, cell_y, dx.signum() as i32, 0),
+            false => PushWall::new(tile, cell_x, cell_y, 0, dy.signum() as i32),
+        };
+        if !wall
+            .cell_at(1)
+            .is_some_and(|cell| self.is_free(cell, from_x, from_y))
+        {
+            return false;
+        }
+        self.game_map.set(cell_x, cell_y, TileId::EMPTY);
+        self.push_walls.push(wall);
+        true
+    }
+
+    // update_push_walls moves the walls being pushed by delta_time seconds.
+    // A wall stops in front of anything solid, an entity or the player at x, y,
+    // and is put back on the map where it stops.
+    pub fn update_push_walls(&mut self, delta_time: f64, x: f64, y: f64) {
+        for index in 0..self.push_walls.len() {
+            let mut wall = self.push_walls[index];
+            if !wall.moving {
+                continue;
+            }
+            wall.update(delta_time, |cell| self.is_free(cell, x, y));
+            if let (false, Some((cell_x, cell_y))) = (wall.moving, wall.cell()) {
+                self.game_map.set(cell_x, cell_y, wall.tile);
+            }
+            self.push_walls[index] = wall;
+        }
     }
+
+    // secrets_found is the number of secret walls pushed so far
+    pub fn secrets_found(&self) -> u32 {
+        self.push_walls.len() as u32
+    }
+
+    // is_free tells whether a cell is open ground with nothing standing on it
+    // or moving into it, the player being at x, y
+    fn is_free(&self, cell: (usize, usize), x: f64, y: f64) -> bool {
+        self.game_map
+            .get(cell.0, cell.1)
+            .is_some_and(|tile| !self.tiles.is_solid(tile) && self.tiles.segments(tile).is_empty())
+            && cell_of(x, y) != Some(cell)
+            && self
+                .entities
+                .iter()
+                .all(|entity| cell_of(entity.x, entity.y) != Some(cell))
+            && self
+                .push_walls
+                .iter()
+                .all(|wall| !wall.moving || !wall.covers(cell))
+    }
+
+    // is_exit tells whether the coordinate lies on an exit tile
+    // or in the same cell as an exit entity
+    pub fn is_exit(&self, x: f64, y: f64) -> bool {
+        self.tile_at(x, y)
+            .is_some_and(|tile| self.tiles.is_exit(tile))
+            || self.entities.iter().any(|entity| {
+                entity.kind == EXIT_ENTITY && cell_of(entity.x, entity.y) == cell_of(x, y)
+            })
+    }
+
+    // apply_fog fogs a color seen at the perpendicular distance
+    pub fn apply_fog(&self, color: u32, distance: f64) -> u32 {
+        self.fog.map_or(color, |fog| fog.apply(color, distance))
+    }
+
+    // sight_limit returns how far along a ray walls can still be seen over a
+    // wall of the tile met at distance from an eye at eye_height. Farther walls
+    // rise above its top only while they are steeper to look up at, which the
+    // tallest wall of the level is up to this distance. A wall lower than the
+    // eye or see through hides nothing.
+    pub fn sight_limit(&self, tile: TileId, distance: f64, eye_height: f64) -> f64 {
+        let top = self.tiles.top(tile) * TILE_SIZE as f64;
+        let max_top = self.max_height * TILE_SIZE as f64;
+        if self.tiles.is_see_through(tile) || top <= eye_height {
+            return f64::INFINITY;
+        }
+        distance * (max_top - eye_height) / (top - eye_height)
+    }
+
+    // blocks_sight tells whether a ray stops at the coordinate
+    pub fn blocks_sight(&self, x: f64, y: f64) -> bool {
+        self.tile_at(x, y)
+            .is_none_or(|tile| self.tiles.blocks_sight(tile))
+    }
+
+    // floor_height is how high the floor is raised at the coordinate in world units
+    pub fn floor_height(&self, x: f64, y: f64) -> f64 {
+        self.tile_at(x, y)
+            .map_or(0.0, |tile| self.tiles.floor_height(tile) * TILE_SIZE as f64)
+    }
+}
+
+// cell_of returns the map cell containing the world coordinate,
+// None for negative coordinates
+fn cell_of(x: f64, y: f64) -> Option<(usize, usize)> {
+    if x < 0.0 || y < 0.0 {
+        return None;
+    }
+    Some((
+        (x / TILE_SIZE as f64).floor() as usize,
+        (y / TILE_SIZE as f64).floor() as usize,
+    ))
+}
+
+// find_doors creates a door for every door tile. A door between walls west
+// and east of it is horizontal, other doors are vertical.
+fn find_doors(grid: &Grid, tiles: &TileRegistry) -> HashMap<(usize, usize), Door> {
+    let is_wall = |x: usize, y: usize| {
+        grid.get(x, y)
+            .is_some_and(|tile| tiles.is_solid(tile) && !tiles.is_door(tile))
+    };
+    let mut doors = HashMap::new();
+    for (y, row) in grid.rows().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            if tiles.is_door(*tile) {
+                let horizontal = x > 0 && is_wall(x - 1, y) && is_wall(x + 1, y);
+                doors.insert((x, y), Door::new(x, y, horizontal));
+            }
+        }
+    }
+    doors
 }
 
 #[cfg(test)]
@@ -75,4 +423,245 @@ mod tests {
         let game = Game::default();
         assert!(game.is_coordinate_solid(11.0 * TILE_SIZE as f64, 12.0 * TILE_SIZE as f64));
     }
+
+    #[test]
+    fn test_default_map_layout() {
+        let game = Game::default();
+        assert_eq!((game.game_map.width(), game.game_map.height()), (20, 13));
+        let rows: Vec<Vec<u16>> = game
+            .game_map
+            .rows()
+            .map(|row| row.iter().map(|tile| tile.0).collect())
+            .collect();
+        assert_eq!(rows[2][15], 8);
+        assert_eq!(rows[4][4..14], [2, 2, 0, 3, 0, 4, 0, 5, 0, 6]);
+        assert_eq!(rows[7][13], 7);
+        assert_eq!(rows[12][13..15], [1, 5]);
+    }
+
+    #[test]
+    fn test_is_coordinate_solid_uses_map_size() {
+        let mut rows = vec![vec![TileId::EMPTY; 64]; 64];
+        rows[63][63] = TileId(1);
+        let tiles = TileRegistry::default();
+        let mut level = Level::from_map_source(String::new(), "0", &tiles).unwrap();
+        level.grid = Grid::from_rows(rows).unwrap();
+        let game = Game::from_level(level, tiles, ActorRegistry::default());
+        let tile = TILE_SIZE as f64;
+        assert!(!game.is_coordinate_solid(62.5 * tile, 63.5 * tile));
+        assert!(game.is_coordinate_solid(63.5 * tile, 63.5 * tile));
+        assert!(game.is_coordinate_solid(64.5 * tile, 10.0 * tile));
+        assert_eq!(game.world_width(), 64.0 * tile);
+    }
+
+    #[test]
+    fn test_solidity_and_sight_come_from_registry() {
+        let tiles = TileRegistry::from_toml(
+            r#"
+            [[tile]]
+            id = 1
+            name = "Invisible wall"
+            blocks_sight = false
+
+            [[tile]]
+            id = 2
+            name = "Fake wall"
+            solid = false
+            "#,
+        )
+        .unwrap();
+        let level = Level::from_map_source(String::from("test"), "12", &tiles).unwrap();
+        let game = Game::from_level(level, tiles, ActorRegistry::default());
+        let tile = TILE_SIZE as f64;
+        assert!(game.is_coordinate_solid(0.5 * tile, 0.5 * tile));
+        assert!(!game.blocks_sight(0.5 * tile, 0.5 * tile));
+        assert!(!game.is_coordinate_solid(1.5 * tile, 0.5 * tile));
+        assert!(game.blocks_sight(1.5 * tile, 0.5 * tile));
+    }
+
+    #[test]
+    fn test_is_exit() {
+        let mut game = Game::default();
+        let tile = TILE_SIZE as f64;
+        assert!(game.is_exit(18.5 * tile, 1.5 * tile));
+        assert!(!game.is_exit(2.5 * tile, 2.5 * tile));
+        game.entities.push(Entity {
+            kind: String::from(EXIT_ENTITY),
+            name: String::from("portal"),
+            x: 2.2 * tile,
+            y: 2.9 * tile,
+            angle: 0.0,
+            properties: Default::default(),
+        });
+        assert!(game.is_exit(2.5 * tile, 2.5 * tile));
+    }
+
+    #[test]
+    fn test_doors() {
+        let tiles = TileRegistry::default();
+        let map = "111111\n1.D1.1\n111D11\n1....1\n111111\n";
+        let level = Level::from_map_source(String::from("doors"), map, &tiles).unwrap();
+        let mut game = Game::from_level(level, tiles, ActorRegistry::default());
+        let tile = TILE_SIZE as f64;
+        assert_eq!(game.doors.len(), 2);
+        assert!(!game.doors[&(2, 1)].horizontal);
+        assert!(game.doors[&(3, 2)].horizontal);
+
+        // closed doors block the player until open enough
+        let (x, y) = (2.5 * tile, 1.5 * tile);
+        assert!(game.is_coordinate_solid(x, y));
+        assert!(game.use_door(x, y));
+        assert!(!game.use_door(1.5 * tile, 1.5 * tile));
+        game.update_doors(0.5, tile, tile);
+        assert!(game.is_coordinate_solid(x, y));
+        game.update_doors(0.5, tile, tile);
+        assert!(!game.is_coordinate_solid(x, y));
+
+        // the door does not close on the player standing in it
+        game.update_doors(60.0, x, y);
+        game.update_doors(60.0, x, y);
+        assert_eq!(game.door_at(x, y).unwrap().opening, 1.0);
+    }
+
+    #[test]
+    fn test_push_secret() {
+        let tiles = TileRegistry::default();
+        let map = "111111\n1.S..1\n1.S.11\n111111\n";
+        let level = Level::from_map_source(String::from("secrets"), map, &tiles).unwrap();
+        let mut game = Game::from_level(level, tiles, ActorRegistry::default());
+        let tile = TILE_SIZE as f64;
+        let secret = game.game_map.get(2, 1).unwrap();
+        assert_eq!((game.secrets, game.secrets_found()), (2, 0));
+
+        // pushed from the west the wall slides east and stops two tiles away
+        let (x, y) = (1.5 * tile, 1.5 * tile);
+        assert!(!game.push_secret(x, y, x, y));
+        assert!(game.push_secret(2.1 * tile, y, x, y));
+        assert_eq!(game.game_map.get(2, 1), Some(TileId::EMPTY));
+        game.update_push_walls(0.5, x, y);
+        assert!(game.is_coordinate_solid(3.0 * tile, y));
+        assert!(!game.is_coordinate_solid(2.4 * tile, y));
+        for _ in 0..24 {
+            game.update_push_walls(0.125, x, y);
+        }
+        assert!(!game.push_walls[0].moving);
+        assert_eq!(game.game_map.get(4, 1), Some(secret));
+        assert!(!game.push_secret(4.5 * tile, y, x, y));
+
+        // the second one has a wall one tile behind it
+        let y = 2.5 * tile;
+        assert!(game.push_secret(2.1 * tile, y, x, y));
+        for _ in 0..24 {
+            game.update_push_walls(0.125, x, y);
+        }
+        assert_eq!(game.game_map.get(3, 2), Some(secret));
+        assert_eq!(game.secrets_found(), 2);
+    }
+
+    #[test]
+    fn test_segments() {
+        let tiles = TileRegistry::default();
+        let map = "11111\n1./.1\n11111\n";
+        let level = Level::from_map_source(String::from("segments"), map, &tiles).unwrap();
+        let game = Game::from_level(level, tiles, ActorRegistry::default());
+        let tile = TILE_SIZE as f64;
+        let y = 1.5 * tile;
+
+        // the diagonal crosses the middle of its cell
+        let hit = game.segment_hit(2.1 * tile, y, 1.5 * tile, y, 0.0).unwrap();
+        assert!((hit.distance - tile).abs() < 1e-9);
+        assert_eq!(game.segment_hit(1.5 * tile, y, 1.5 * tile, y, 0.0), None);
+
+        // the player walks into the open half but not through the diagonal
+        assert!(!game.is_coordinate_solid(2.2 * tile, y));
+        assert!(!game.hits_segment(2.1 * tile, y, 2.2 * tile, y));
+        assert!(game.hits_segment(2.4 * tile, y, 2.6 * tile, y));
+        assert!(game.hits_segment(2.4 * tile, y, 2.49 * tile, y));
+        // and can always back away from it
+        assert!(!game.hits_segment(2.49 * tile, y, 2.45 * tile, y));
+    }
+
+    #[test]
+    fn test_portals() {
+        let source = "name = \"Portals\"\nmap = \"\"\"\n1111111\n1O...O1\n1111111\n\"\"\"\n\
+            [[portal]]\na = [1, 1]\nb = [5, 1]\n";
+        let tiles = TileRegistry::default();
+        let level = Level::from_toml(source, &tiles).unwrap();
+        let game = Game::from_level(level, tiles, ActorRegistry::default());
+        let tile = TILE_SIZE as f64;
+        let y = 1.5 * tile;
+
+        // a ray hitting the east face of one portal goes on west from
+        // the east face of the other, as if both were the same cell
+        let (x, warped_y, angle) = game.warp(2.0 * tile, y, PI).unwrap();
+        assert!((x - (6.0 * tile - PORTAL_NUDGE)).abs() < 1e-9);
+        assert_eq!((warped_y, angle), (y, PI));
+        assert_eq!(game.warp(3.0 * tile, y, PI), None);
+
+        // the player comes out of the far side of the joined cell
+        let (x, y, _) = game.pass_through(1.9 * tile, y, PI).unwrap();
+        assert_eq!(cell_of(x, y), Some((4, 1)));
+        assert!(!game.is_coordinate_solid(x, y));
+    }
+
+    #[test]
+    fn test_mirrors() {
+        let mut game = Game::default();
+        // a ray heading north east bounces off a wall along a vertical grid
+        // line heading north west, and off a horizontal one heading south east
+        let (x, y, angle) = game.reflect(128.0, 50.0, -PI / 4.0, true);
+        assert!((x - (128.0 - PORTAL_NUDGE / 2f64.sqrt())).abs() < 1e-9 && y < 50.0);
+        assert!((angle - 1.25 * PI).abs() < 1e-9);
+        let (_, _, angle) = game.reflect(100.0, 64.0, -PI / 4.0, false);
+        assert!((angle - PI / 4.0).abs() < 1e-9);
+
+        // views seen by several rays are only kept once
+        assert_eq!(game.view_id(View::IDENTITY), 0);
+        let mirror = View::new(128.0, 50.0, PI, (128.0, 50.0), 0.0, true);
+        assert_eq!(game.view_id(mirror), 1);
+        assert_eq!(game.view_id(mirror), 1);
+        assert_eq!(game.views.len(), 2);
+    }
+
+    #[test]
+    fn test_sight_limit() {
+        let tile = TILE_SIZE as f64;
+        let game = Game::default();
+        assert_eq!(game.max_height, 1.0);
+        assert_eq!(game.sight_limit(TileId(1), 100.0, tile / 2.0), 100.0);
+        // see through walls never hide what is behind them
+        for symbol in ['g', '#', ':'] {
+            let see_through = game.tiles.by_symbol(symbol).unwrap();
+            assert_eq!(
+                game.sight_limit(see_through, 100.0, tile / 2.0),
+                f64::INFINITY
+            );
+        }
+
+        // with towers about, towers rise above lower walls up to some distance
+        let tiles = TileRegistry::default();
+        let map = "1111T\n1.h.1\n11111\n";
+        let level = Level::from_map_source(String::from("heights"), map, &tiles).unwrap();
+        let low = tiles.by_symbol('h').unwrap();
+        let tower = tiles.by_symbol('T').unwrap();
+        let game = Game::from_level(level, tiles, ActorRegistry::default());
+        assert_eq!(game.max_height, 2.0);
+        assert_eq!(game.sight_limit(tower, 100.0, tile / 2.0), 100.0);
+        assert_eq!(game.sight_limit(TileId(1), 100.0, tile / 2.0), 300.0);
+        // the top of a low wall is below the eye, anything may show over it
+        assert_eq!(game.sight_limit(low, 100.0, tile / 2.0), f64::INFINITY);
+        assert_eq!(game.sight_limit(tower, 100.0, 3.0 * tile), f64::INFINITY);
+        // raised floors only hide what is behind them from eyes below their top
+        let step = game.tiles.by_symbol('b').unwrap();
+        assert_eq!(game.sight_limit(step, 100.0, tile / 2.0), f64::INFINITY);
+        assert_eq!(game.sight_limit(step, 100.0, tile / 8.0), 1500.0);
+    }
+
+    #[test]
+    fn test_title() {
+        let mut game = Game::default();
+        assert_eq!(game.title(), "Entrance (par 1:30)");
+        game.par_time = None;
+        assert_eq!(game.title(), "Entrance");
+    }
 }
@@ -1 +1,3 @@
+pub mod door;
 pub mod game;
+pub mod push_wall;
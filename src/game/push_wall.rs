@@ -0,0 +1,180 @@
+use crate::{tile::tile::TileId, window::window::TILE_SIZE};
+
+// Tiles a push wall travels before it stops for good
+pub const PUSH_DISTANCE: u32 = 2;
+// Seconds a push wall takes to move one tile
+pub const PUSH_TIME: f64 = 1.0;
+
+// PushWall is a secret wall pushed away from the player. It starts in the
+// cell x, y and moves along dx, dy by offset tiles. While it moves it sits
+// between two cells, off the grid, and is drawn as a box of its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PushWall {
+    pub tile: TileId,
+    pub x: usize,
+    pub y: usize,
+    pub dx: i32,
+    pub dy: i32,
+    pub offset: f64,
+    pub moving: bool,
+}
+
+impl PushWall {
+    pub fn new(tile: TileId, x: usize, y: usize, dx: i32, dy: i32) -> Self {
+        PushWall {
+            tile,
+            x,
+            y,
+            dx,
+            dy,
+            offset: 0.0,
+            moving: true,
+        }
+    }
+
+    // cell_at returns the cell steps tiles along the push from the start
+    pub fn cell_at(&self, steps: u32) -> Option<(usize, usize)> {
+        Some((
+            self.x
+                .checked_add_signed(self.dx as isize * steps as isize)?,
+            self.y
+                .checked_add_signed(self.dy as isize * steps as isize)?,
+        ))
+    }
+
+    // cell is the cell the wall has fully entered last
+    pub fn cell(&self) -> Option<(usize, usize)> {
+        self.cell_at(self.offset.floor() as u32)
+    }
+
+    // covers tells whether the wall overlaps the cell, a moving wall
+    // overlaps the cell it has entered last and the next one
+    pub fn covers(&self, cell: (usize, usize)) -> bool {
+        let steps = self.offset.floor() as u32;
+        self.cell_at(steps) == Some(cell) || (self.moving && self.cell_at(steps + 1) == Some(cell))
+    }
+
+    // update moves the wall by delta_time seconds. Each time the wall fills
+    // a cell it only goes on when is_free lets it into the next one.
+    pub fn update(&mut self, delta_time: f64, is_free: impl Fn((usize, usize)) -> bool) {
+        if !self.moving {
+            return;
+        }
+        let steps = self.offset.floor() as u32;
+        self.offset = (self.offset + delta_time / PUSH_TIME).min(PUSH_DISTANCE as f64);
+        for step in steps + 1..=self.offset.floor() as u32 {
+            if step == PUSH_DISTANCE || !self.cell_at(step + 1).is_some_and(&is_free) {
+                self.offset = step as f64;
+                self.moving = false;
+                return;
+            }
+        }
+    }
+
+    // origin is the top left corner of the wall in world units
+    pub fn origin(&self) -> (f64, f64) {
+        let tile = TILE_SIZE as f64;
+        (
+            (self.x as f64 + self.dx as f64 * self.offset) * tile,
+            (self.y as f64 + self.dy as f64 * self.offset) * tile,
+        )
+    }
+
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        let (left, top) = self.origin();
+        let tile = TILE_SIZE as f64;
+        (left..left + tile).contains(&x) && (top..top + tile).contains(&y)
+    }
+
+    // intersect returns the distance at which a ray from x, y along angle
+    // hits the wall and whether it hits a face along a vertical grid line
+    pub fn intersect(&self, x: f64, y: f64, angle: f64) -> Option<(f64, bool)> {
+        let (left, top) = self.origin();
+        let tile = TILE_SIZE as f64;
+        let (sin, cos) = angle.sin_cos();
+        let (mut near, mut far) = (0.0, f64::INFINITY);
+        let mut is_vertical = false;
+        for (start, direction, low, vertical) in [(x, cos, left, true), (y, sin, top, false)] {
+            if direction.abs() < f64::EPSILON {
+                if !(low..=low + tile).contains(&start) {
+                    return None;
+                }
+                continue;
+            }
+            let (enter, leave) = match direction > 0.0 {
+                true => ((low - start) / direction, (low + tile - start) / direction),
+                false => ((low + tile - start) / direction, (low - start) / direction),
+            };
+            if enter > near {
+                near = enter;
+                is_vertical = vertical;
+            }
+            far = leave.min(far);
+        }
+        (near <= far).then_some((near, is_vertical))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    // eighths of a second add up without rounding errors
+    fn run(wall: &mut PushWall, seconds: f64, is_free: impl Fn((usize, usize)) -> bool) {
+        for _ in 0..(seconds * 8.0).round() as usize {
+            wall.update(0.125, &is_free);
+        }
+    }
+
+    #[test]
+    fn test_push_wall_moves_two_tiles() {
+        let mut wall = PushWall::new(TileId(1), 3, 3, 1, 0);
+        run(&mut wall, PUSH_TIME / 2.0, |_| true);
+        assert_eq!(
+            wall.origin(),
+            (3.5 * TILE_SIZE as f64, 3.0 * TILE_SIZE as f64)
+        );
+        assert!(wall.moving);
+        assert!(wall.covers((3, 3)) && wall.covers((4, 3)));
+        run(&mut wall, PUSH_TIME * 3.0, |_| true);
+        assert!(!wall.moving);
+        assert_eq!(wall.offset, PUSH_DISTANCE as f64);
+        assert_eq!(wall.cell(), Some((5, 3)));
+    }
+
+    #[test]
+    fn test_push_wall_stops_when_blocked() {
+        let mut wall = PushWall::new(TileId(1), 3, 3, 0, -1);
+        run(&mut wall, PUSH_TIME * 3.0, |cell| cell != (3, 1));
+        assert!(!wall.moving);
+        assert_eq!(wall.cell(), Some((3, 2)));
+        assert!(wall.covers((3, 2)) && !wall.covers((3, 1)));
+        assert_eq!(wall.cell_at(3), Some((3, 0)));
+        assert_eq!(wall.cell_at(4), None);
+    }
+
+    #[test]
+    fn test_intersect() {
+        let tile = TILE_SIZE as f64;
+        let mut wall = PushWall::new(TileId(1), 2, 0, 1, 0);
+        wall.offset = 0.25;
+
+        // looking east along the middle of the row hits the west face
+        let (distance, vertical) = wall.intersect(0.5 * tile, 0.5 * tile, 0.0).unwrap();
+        assert!((distance - 1.75 * tile).abs() < 1e-9);
+        assert!(vertical);
+
+        // looking north from under the wall hits its south face
+        let (distance, vertical) = wall.intersect(3.0 * tile, 2.0 * tile, 1.5 * PI).unwrap();
+        assert!((distance - tile).abs() < 1e-9);
+        assert!(!vertical);
+
+        // the quarter of the cell left behind is open
+        assert_eq!(wall.intersect(2.1 * tile, 2.0 * tile, 1.5 * PI), None);
+        assert_eq!(wall.intersect(0.5 * tile, 0.5 * tile, PI), None);
+        assert!(wall.contains(3.2 * tile, 0.5 * tile));
+        assert!(!wall.contains(2.2 * tile, 0.5 * tile));
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+
+use crate::{map::grid::Grid, tile::tile::TileId};
+
+use super::{generator::WALL, rng::Rng};
+
+const FILL_PROBABILITY: f64 = 0.45;
+const SMOOTHING_STEPS: usize = 5;
+// a cell becomes a wall when at least this many cells
+// of the 3x3 block around it are walls
+const WALL_THRESHOLD: usize = 5;
+
+// caves fills the map with noise and smooths it with a cellular automaton,
+// then keeps only the largest open area so that every cave is reachable
+pub fn caves(width: usize, height: usize, rng: &mut Rng) -> Grid {
+    let mut grid = Grid::new(width, height, WALL);
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            if !rng.chance(FILL_PROBABILITY) {
+                grid.set(x, y, TileId::EMPTY);
+            }
+        }
+    }
+
+    for _ in 0..SMOOTHING_STEPS {
+        let mut next = grid.clone();
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let walls = (y - 1..=y + 1)
+                    .flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny)))
+                    .filter(|&(nx, ny)| grid.get(nx, ny) != Some(TileId::EMPTY))
+                    .count();
+                next.set(
+                    x,
+                    y,
+                    if walls >= WALL_THRESHOLD {
+                        WALL
+                    } else {
+                        TileId::EMPTY
+                    },
+                );
+            }
+        }
+        grid = next;
+    }
+
+    keep_largest_area(&mut grid);
+    grid
+}
+
+// keep_largest_area fills every open area but the largest with walls,
+// a map left without any open cell gets its centre opened
+fn keep_largest_area(grid: &mut Grid) {
+    let (width, height) = (grid.width(), grid.height());
+    let mut area = vec![usize::MAX; width * height];
+    let mut sizes = Vec::new();
+
+    for start in 0..width * height {
+        if area[start] != usize::MAX
+            || grid.get(start % width, start / width) != Some(TileId::EMPTY)
+        {
+            continue;
+        }
+        let id = sizes.len();
+        let mut size = 0;
+        let mut queue = VecDeque::from([start]);
+        area[start] = id;
+        while let Some(cell) = queue.pop_front() {
+            size += 1;
+            let (x, y) = (cell % width, cell / width);
+            for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                let next = ny * width + nx;
+                if area[next] == usize::MAX && grid.get(nx, ny) == Some(TileId::EMPTY) {
+                    area[next] = id;
+                    queue.push_back(next);
+                }
+            }
+        }
+        sizes.push(size);
+    }
+
+    let Some(largest) = (0..sizes.len()).max_by_key(|id| sizes[*id]) else {
+        grid.set(width / 2, height / 2, TileId::EMPTY);
+        return;
+    };
+    for (cell, id) in area.iter().enumerate() {
+        if *id != usize::MAX && *id != largest {
+            grid.set(cell % width, cell / width, WALL);
+        }
+    }
+}
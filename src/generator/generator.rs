@@ -0,0 +1,142 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    level::level::{Level, Surface, DEFAULT_CEILING_COLOR, DEFAULT_FLOOR_COLOR},
+    map::grid::Grid,
+    tile::{registry::TileRegistry, tile::TileId},
+};
+
+use super::{caves::caves, maze::maze, rng::Rng, rooms::rooms};
+
+// Tile used for every wall of a generated map
+pub const WALL: TileId = TileId(1);
+
+// Generated maps are at least this many tiles wide and high
+pub const MIN_SIZE: usize = 5;
+pub const DEFAULT_SIZE: (usize, usize) = (32, 32);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Rooms,
+    Maze,
+    Caves,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rooms" => Ok(Algorithm::Rooms),
+            "maze" => Ok(Algorithm::Maze),
+            "caves" => Ok(Algorithm::Caves),
+            _ => Err(format!(
+                "Unknown generator {}, expected rooms, maze or caves",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Algorithm::Rooms => "rooms",
+            Algorithm::Maze => "maze",
+            Algorithm::Caves => "caves",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// generate builds a width by height map closed by walls in which every
+// open cell can be walked to. The same seed always gives the same map.
+pub fn generate(
+    algorithm: Algorithm,
+    width: usize,
+    height: usize,
+    seed: u64,
+) -> Result<Grid, String> {
+    if width < MIN_SIZE || height < MIN_SIZE {
+        return Err(format!(
+            "Generated maps must be at least {}x{}, got {}x{}",
+            MIN_SIZE, MIN_SIZE, width, height
+        ));
+    }
+    let mut rng = Rng::new(seed);
+    Ok(match algorithm {
+        Algorithm::Rooms => rooms(width, height, &mut rng),
+        Algorithm::Maze => maze(width, height, &mut rng),
+        Algorithm::Caves => caves(width, height, &mut rng),
+    })
+}
+
+pub fn generate_level(
+    algorithm: Algorithm,
+    (width, height): (usize, usize),
+    seed: u64,
+    tiles: &TileRegistry,
+) -> Result<Level, String> {
+    let grid = generate(algorithm, width, height, seed)?;
+    Ok(Level {
+        name: format!("Generated {} {}", algorithm, seed),
+        spawn: Level::default_spawn(&grid, tiles),
+        grid,
+        ceiling: Surface::color(DEFAULT_CEILING_COLOR),
+        floor: Surface::color(DEFAULT_FLOOR_COLOR),
+        sky: None,
+        fog: None,
+        par_time: None,
+        entities: Vec::new(),
+        portals: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::validate::validate;
+
+    const ALGORITHMS: [Algorithm; 3] = [Algorithm::Rooms, Algorithm::Maze, Algorithm::Caves];
+
+    #[test]
+    fn test_same_seed_same_map() {
+        for algorithm in ALGORITHMS {
+            let first = generate(algorithm, 24, 18, 42).unwrap();
+            assert_eq!(first, generate(algorithm, 24, 18, 42).unwrap());
+            assert_ne!(first, generate(algorithm, 24, 18, 43).unwrap());
+            assert_eq!((first.width(), first.height()), (24, 18));
+        }
+    }
+
+    #[test]
+    fn test_generated_levels_are_valid() {
+        let tiles = TileRegistry::default();
+        for algorithm in ALGORITHMS {
+            for (seed, size) in [(0, (5, 5)), (1, (6, 9)), (7, (32, 32)), (99, (64, 20))] {
+                let level = generate_level(algorithm, size, seed, &tiles).unwrap();
+                let diagnostics = validate(
+                    &level.grid,
+                    &level.spawn,
+                    &level.portals,
+                    &level.entities,
+                    &tiles,
+                );
+                assert!(
+                    diagnostics.is_empty(),
+                    "{} seed {}: {:?}",
+                    algorithm,
+                    seed,
+                    diagnostics
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_algorithm_from_str() {
+        assert_eq!("maze".parse(), Ok(Algorithm::Maze));
+        assert!("dungeon".parse::<Algorithm>().is_err());
+        assert!(generate(Algorithm::Rooms, 4, 20, 0).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{map::grid::Grid, tile::tile::TileId};
+
+use super::{generator::WALL, rng::Rng};
+
+// maze carves a perfect maze with a recursive backtracker.
+// Cells sit on odd coordinates and the walls between them on even ones,
+// so with an even size the last column or row stays solid.
+pub fn maze(width: usize, height: usize, rng: &mut Rng) -> Grid {
+    let mut grid = Grid::new(width, height, WALL);
+    let columns = (width - 1) / 2;
+    let rows = (height - 1) / 2;
+    let mut visited = vec![false; columns * rows];
+
+    let start = (rng.range(0, columns), rng.range(0, rows));
+    let mut stack = vec![start];
+    visited[start.1 * columns + start.0] = true;
+    grid.set(start.0 * 2 + 1, start.1 * 2 + 1, TileId::EMPTY);
+
+    while let Some(&(x, y)) = stack.last() {
+        let mut next: Vec<(usize, usize)> = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ]
+        .into_iter()
+        .filter(|&(nx, ny)| nx < columns && ny < rows && !visited[ny * columns + nx])
+        .collect();
+
+        if next.is_empty() {
+            stack.pop();
+            continue;
+        }
+        rng.shuffle(&mut next);
+        let (nx, ny) = next[0];
+        visited[ny * columns + nx] = true;
+        // open the wall between the two cells and the new cell itself
+        grid.set(x + nx + 1, y + ny + 1, TileId::EMPTY);
+        grid.set(nx * 2 + 1, ny * 2 + 1, TileId::EMPTY);
+        stack.push((nx, ny));
+    }
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maze_has_no_open_squares() {
+        let grid = maze(21, 15, &mut Rng::new(3));
+        for y in 0..grid.height() - 1 {
+            for x in 0..grid.width() - 1 {
+                let open = [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)]
+                    .iter()
+                    .all(|&(x, y)| grid.get(x, y) == Some(TileId::EMPTY));
+                assert!(!open, "open square at {}, {}", x, y);
+            }
+        }
+    }
+}
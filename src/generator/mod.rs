@@ -0,0 +1,5 @@
+pub mod caves;
+pub mod generator;
+pub mod maze;
+pub mod rng;
+pub mod rooms;
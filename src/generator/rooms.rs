@@ -0,0 +1,83 @@
+use crate::{map::grid::Grid, tile::tile::TileId};
+
+use super::{generator::WALL, rng::Rng};
+
+const MIN_ROOM_SIZE: usize = 3;
+const MAX_ROOM_SIZE: usize = 8;
+// how many times a room is placed before giving up on it
+const ATTEMPTS: usize = 60;
+
+#[derive(Debug, Clone, Copy)]
+struct Room {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Room {
+    fn centre(&self) -> (usize, usize) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+
+    // rooms must keep at least one wall between them
+    fn overlaps(&self, other: &Room) -> bool {
+        self.x <= other.x + other.width
+            && other.x <= self.x + self.width
+            && self.y <= other.y + other.height
+            && other.y <= self.y + self.height
+    }
+}
+
+// rooms scatters non overlapping rectangular rooms over the map
+// and joins each one to the previous with an L shaped corridor
+pub fn rooms(width: usize, height: usize, rng: &mut Rng) -> Grid {
+    let mut grid = Grid::new(width, height, WALL);
+    let max_width = MAX_ROOM_SIZE.min(width - 2);
+    let max_height = MAX_ROOM_SIZE.min(height - 2);
+    let mut rooms: Vec<Room> = Vec::new();
+
+    for _ in 0..ATTEMPTS {
+        let room_width = rng.range(MIN_ROOM_SIZE.min(max_width), max_width + 1);
+        let room_height = rng.range(MIN_ROOM_SIZE.min(max_height), max_height + 1);
+        let room = Room {
+            x: rng.range(1, width - room_width),
+            y: rng.range(1, height - room_height),
+            width: room_width,
+            height: room_height,
+        };
+        if rooms.iter().any(|other| room.overlaps(other)) {
+            continue;
+        }
+
+        for y in room.y..room.y + room.height {
+            for x in room.x..room.x + room.width {
+                grid.set(x, y, TileId::EMPTY);
+            }
+        }
+        if let Some(previous) = rooms.last() {
+            corridor(&mut grid, previous.centre(), room.centre(), rng.chance(0.5));
+        }
+        rooms.push(room);
+    }
+    grid
+}
+
+fn corridor(
+    grid: &mut Grid,
+    (x1, y1): (usize, usize),
+    (x2, y2): (usize, usize),
+    horizontal_first: bool,
+) {
+    let corner = match horizontal_first {
+        true => (x2, y1),
+        false => (x1, y2),
+    };
+    for (from, to) in [((x1, y1), corner), (corner, (x2, y2))] {
+        for x in from.0.min(to.0)..=from.0.max(to.0) {
+            for y in from.1.min(to.1)..=from.1.max(to.1) {
+                grid.set(x, y, TileId::EMPTY);
+            }
+        }
+    }
+}
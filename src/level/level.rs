@@ -0,0 +1,640 @@
+use std::{collections::BTreeMap, f64::consts::PI, fs, path::Path};
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{
+    map::{
+        grid::Grid,
+        parser::{parse_map, MapParseError},
+    },
+    tile::registry::TileRegistry,
+    utils::color::{mix_color, parse_color},
+    window::window::TILE_SIZE,
+};
+
+use super::{
+    portal::Portal,
+    tiled::{load_tmj, load_tmx},
+};
+
+pub const DEFAULT_LEVEL: &str = include_str!("../../maps/default.toml");
+
+// Entities of this kind end the level when the player enters their cell
+pub const EXIT_ENTITY: &str = "exit";
+
+// Panorama drawn over sky tiles of levels that do not pick one
+pub const DEFAULT_SKY_TEXTURE: &str = "sky";
+
+pub const DEFAULT_FOG_COLOR: u32 = 0xFF000000;
+pub const DEFAULT_CEILING_COLOR: u32 = 0xFF444444;
+pub const DEFAULT_FLOOR_COLOR: u32 = 0xFF777777;
+
+// Spawn is where the player starts, in world units and radians
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spawn {
+    pub x: f64,
+    pub y: f64,
+    pub angle: f64,
+}
+
+// Surface describes the ceiling or the floor of a level,
+// the color is used wherever the texture is not drawn
+#[derive(Debug, Clone, PartialEq)]
+pub struct Surface {
+    pub color: u32,
+    pub texture: Option<String>,
+}
+
+impl Surface {
+    pub fn color(color: u32) -> Self {
+        Surface {
+            color,
+            texture: None,
+        }
+    }
+}
+
+// Sky is the panorama drawn where the ceiling is open,
+// which is over sky tiles or, when everywhere is set, over the whole level
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sky {
+    pub texture: String,
+    pub everywhere: bool,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Sky {
+            texture: String::from(DEFAULT_SKY_TEXTURE),
+            everywhere: false,
+        }
+    }
+}
+
+// Falloff is how quickly fog thickens between its start and end distances
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Falloff {
+    Linear,
+    Exponential,
+}
+
+// Fog blends pixels towards its colour with distance, a black fog darkens them.
+// Distances are in world units, nothing is fogged before start and
+// everything is fully fogged past end.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fog {
+    pub color: u32,
+    pub start: f64,
+    pub end: f64,
+    pub falloff: Falloff,
+}
+
+impl Fog {
+    // The exponential falloff leaves this much of a colour at the end distance
+    const EXPONENTIAL_REMAINDER: f64 = 1.0 / 256.0;
+
+    pub fn new(color: u32, start: f64, end: f64, falloff: Falloff) -> Result<Self, String> {
+        if !(start >= 0.0 && end > start) {
+            return Err(format!(
+                "fog must start before it ends, got {} to {}",
+                start, end
+            ));
+        }
+        Ok(Fog {
+            color,
+            start,
+            end,
+            falloff,
+        })
+    }
+
+    // amount is how much fog covers something at distance, from 0 to 1
+    pub fn amount(&self, distance: f64) -> f64 {
+        if distance >= self.end {
+            return 1.0;
+        }
+        let t = ((distance - self.start) / (self.end - self.start)).max(0.0);
+        match self.falloff {
+            Falloff::Linear => t,
+            Falloff::Exponential => 1.0 - Self::EXPONENTIAL_REMAINDER.powf(t),
+        }
+    }
+
+    pub fn apply(&self, color: u32, distance: f64) -> u32 {
+        mix_color(color, self.color, self.amount(distance))
+    }
+}
+
+// Entity is an object placed in the level such as a prop or a light,
+// its position is in world units and its angle in radians
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    pub kind: String,
+    pub name: String,
+    pub x: f64,
+    pub y: f64,
+    pub angle: f64,
+    pub properties: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    pub name: String,
+    pub grid: Grid,
+    pub spawn: Spawn,
+    pub ceiling: Surface,
+    pub floor: Surface,
+    pub sky: Option<Sky>,
+    pub fog: Option<Fog>,
+    // par_time is the expected completion time in seconds
+    pub par_time: Option<f64>,
+    pub entities: Vec<Entity>,
+    pub portals: Vec<Portal>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawLevel {
+    name: String,
+    par_time: Option<f64>,
+    spawn: Option<RawSpawn>,
+    ceiling: Option<RawSurface>,
+    floor: Option<RawSurface>,
+    sky: Option<RawSky>,
+    fog: Option<RawFog>,
+    map: Spanned<String>,
+    #[serde(default, rename = "entity")]
+    entities: Vec<RawEntity>,
+    #[serde(default, rename = "portal")]
+    portals: Vec<RawPortal>,
+}
+
+// Entity positions are given in tiles and angles in degrees
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawEntity {
+    kind: String,
+    #[serde(default)]
+    name: String,
+    x: f64,
+    y: f64,
+    #[serde(default)]
+    angle: f64,
+    #[serde(default)]
+    properties: BTreeMap<String, String>,
+}
+
+// Fog distances are given in tiles
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawFog {
+    color: Option<String>,
+    start: f64,
+    end: f64,
+    #[serde(default = "default_falloff")]
+    falloff: Falloff,
+}
+
+// Portals join two cells given as [x, y], turn is in degrees
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPortal {
+    a: [usize; 2],
+    b: [usize; 2],
+    #[serde(default)]
+    turn: i64,
+}
+
+fn default_falloff() -> Falloff {
+    Falloff::Linear
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSky {
+    texture: Option<String>,
+    #[serde(default)]
+    everywhere: bool,
+}
+
+// Spawn positions are given in tiles and angles in degrees
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSpawn {
+    x: f64,
+    y: f64,
+    #[serde(default)]
+    angle: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSurface {
+    Color(String),
+    Full {
+        color: Option<String>,
+        texture: Option<String>,
+    },
+}
+
+impl RawSurface {
+    fn into_surface(self, default_color: u32) -> Result<Surface, String> {
+        let (color, texture) = match self {
+            RawSurface::Color(color) => (Some(color), None),
+            RawSurface::Full { color, texture } => (color, texture),
+        };
+        let color = match color {
+            Some(color) => parse_color(&color)?,
+            None => default_color,
+        };
+        Ok(Surface { color, texture })
+    }
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self::from_toml(DEFAULT_LEVEL, &TileRegistry::default())
+            .expect("default level should parse")
+    }
+}
+
+impl Level {
+    // from_file loads a TOML level, a Tiled map (.tmx or .tmj) or,
+    // for any other extension, a plain text map using the default level settings
+    pub fn from_file(path: &str, tiles: &TileRegistry) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read level {}: {}", path, err))?;
+        let path = Path::new(path);
+        let name = path
+            .file_stem()
+            .map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+        let level = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&source, tiles),
+            Some("tmx") => load_tmx(&source, name, tiles),
+            Some("tmj") | Some("json") => load_tmj(&source, name, tiles),
+            _ => Self::from_map_source(name, &source, tiles).map_err(|err| err.to_string()),
+        };
+        level.map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    pub fn from_map_source(
+        name: String,
+        source: &str,
+        tiles: &TileRegistry,
+    ) -> Result<Self, MapParseError> {
+        let grid = parse_map(source, tiles)?;
+        Ok(Level {
+            name,
+            spawn: Self::default_spawn(&grid, tiles),
+            grid,
+            ceiling: Surface::color(DEFAULT_CEILING_COLOR),
+            floor: Surface::color(DEFAULT_FLOOR_COLOR),
+            sky: None,
+            fog: None,
+            par_time: None,
+            entities: Vec::new(),
+            portals: Vec::new(),
+        })
+    }
+
+    pub fn from_toml(source: &str, tiles: &TileRegistry) -> Result<Self, String> {
+        let raw: RawLevel = toml::from_str(source).map_err(|err| err.to_string())?;
+
+        let map_start = raw.map.span().start;
+        let grid = parse_map(raw.map.get_ref(), tiles).map_err(|mut err| {
+            // report positions relative to the level file
+            err.line += map_line_offset(source, map_start);
+            err.to_string()
+        })?;
+
+        let fog = match raw.fog {
+            Some(fog) => {
+                let color = match fog.color {
+                    Some(color) => parse_color(&color).map_err(|err| format!("fog: {}", err))?,
+                    None => DEFAULT_FOG_COLOR,
+                };
+                let tile = TILE_SIZE as f64;
+                Some(
+                    Fog::new(color, fog.start * tile, fog.end * tile, fog.falloff)
+                        .map_err(|err| format!("fog: {}", err))?,
+                )
+            }
+            None => None,
+        };
+
+        let portals = raw
+            .portals
+            .iter()
+            .enumerate()
+            .map(|(index, portal)| {
+                Self::portal(portal, &grid, tiles)
+                    .map_err(|err| format!("portal {}: {}", index + 1, err))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let spawn = match raw.spawn {
+            Some(spawn) => Spawn {
+                x: spawn.x * TILE_SIZE as f64,
+                y: spawn.y * TILE_SIZE as f64,
+                angle: spawn.angle.to_radians(),
+            },
+            None => Self::default_spawn(&grid, tiles),
+        };
+
+        Ok(Level {
+            name: raw.name,
+            grid,
+            spawn,
+            ceiling: Self::surface(raw.ceiling, DEFAULT_CEILING_COLOR, "ceiling")?,
+            floor: Self::surface(raw.floor, DEFAULT_FLOOR_COLOR, "floor")?,
+            sky: raw.sky.map(|sky| Sky {
+                texture: sky
+                    .texture
+                    .unwrap_or_else(|| String::from(DEFAULT_SKY_TEXTURE)),
+                everywhere: sky.everywhere,
+            }),
+            fog,
+            par_time: raw.par_time,
+            entities: raw
+                .entities
+                .into_iter()
+                .map(|entity| Entity {
+                    kind: entity.kind,
+                    name: entity.name,
+                    x: entity.x * TILE_SIZE as f64,
+                    y: entity.y * TILE_SIZE as f64,
+                    angle: entity.angle.to_radians(),
+                    properties: entity.properties,
+                })
+                .collect(),
+            portals,
+        })
+    }
+
+    // portal checks that a portal joins two distinct portal tiles
+    // and turns by whole quarter turns
+    fn portal(raw: &RawPortal, grid: &Grid, tiles: &TileRegistry) -> Result<Portal, String> {
+        for [x, y] in [raw.a, raw.b] {
+            if !grid.get(x, y).is_some_and(|tile| tiles.is_portal(tile)) {
+                return Err(format!("cell ({}, {}) is not a portal tile", x, y));
+            }
+        }
+        if raw.a == raw.b {
+            return Err(String::from("a portal cannot lead to itself"));
+        }
+        if raw.turn % 90 != 0 {
+            return Err(format!("turn {} is not a multiple of 90 degrees", raw.turn));
+        }
+        Ok(Portal {
+            a: (raw.a[0], raw.a[1]),
+            b: (raw.b[0], raw.b[1]),
+            turns: (raw.turn / 90).rem_euclid(4) as u32,
+        })
+    }
+
+    fn surface(raw: Option<RawSurface>, default_color: u32, what: &str) -> Result<Surface, String> {
+        match raw {
+            Some(raw) => raw
+                .into_surface(default_color)
+                .map_err(|err| format!("{}: {}", what, err)),
+            None => Ok(Surface::color(default_color)),
+        }
+    }
+
+    // has_exit tells whether the level can be finished,
+    // either through an exit tile or an exit entity
+    pub fn has_exit(&self, tiles: &TileRegistry) -> bool {
+        self.grid.rows().flatten().any(|tile| tiles.is_exit(*tile))
+            || self
+                .entities
+                .iter()
+                .any(|entity| entity.kind == EXIT_ENTITY)
+    }
+
+    // default_spawn places the player in the middle of the map facing south,
+    // or in the first passable cell when the middle is solid
+    pub fn default_spawn(grid: &Grid, tiles: &TileRegistry) -> Spawn {
+        let tile = TILE_SIZE as f64;
+        let centre = (grid.width() / 2, grid.height() / 2);
+        let passable =
+            |(x, y): (usize, usize)| grid.get(x, y).is_some_and(|tile| !tiles.is_solid(tile));
+
+        let (x, y) = if passable(centre) {
+            (
+                grid.width() as f64 * tile / 2.0,
+                grid.height() as f64 * tile / 2.0,
+            )
+        } else {
+            let mut cells = (0..grid.height()).flat_map(|y| (0..grid.width()).map(move |x| (x, y)));
+            let (x, y) = cells.find(|cell| passable(*cell)).unwrap_or(centre);
+            ((x as f64 + 0.5) * tile, (y as f64 + 0.5) * tile)
+        };
+
+        Spawn {
+            x,
+            y,
+            angle: PI / 2.0,
+        }
+    }
+}
+
+// map_line_offset returns how many lines precede the map string contents
+fn map_line_offset(source: &str, start: usize) -> usize {
+    let offset = source[..start].matches('\n').count();
+    // a newline right after the opening quotes is not part of the string
+    match source[start..]
+        .strip_prefix("\"\"\"")
+        .or_else(|| source[start..].strip_prefix("'''"))
+    {
+        Some(rest) if rest.starts_with('\n') || rest.starts_with("\r\n") => offset + 1,
+        _ => offset,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile::tile::TileId;
+
+    const LEVEL: &str = r##"
+name = "Test chamber"
+par_time = 45.0
+ceiling = "#101010"
+floor = { color = "#202020", texture = "stone" }
+map = """
+1111
+1..1
+1..1
+1111
+"""
+
+[spawn]
+x = 1.5
+y = 2.5
+angle = 180
+
+[sky]
+texture = "dusk"
+everywhere = true
+
+[fog]
+color = "#808080"
+start = 2
+end = 10
+falloff = "exponential"
+
+[[entity]]
+kind = "barrel"
+x = 2.5
+y = 1.5
+
+[[entity]]
+kind = "prop"
+name = "Lamp by the door"
+x = 1.5
+y = 1.5
+angle = 90
+properties = { sprite = "lamp" }
+"##;
+
+    #[test]
+    fn test_from_toml() {
+        let level = Level::from_toml(LEVEL, &TileRegistry::default()).unwrap();
+        let tile = TILE_SIZE as f64;
+        assert_eq!(level.name, "Test chamber");
+        assert_eq!(level.par_time, Some(45.0));
+        assert_eq!(level.ceiling, Surface::color(0xFF101010));
+        assert_eq!(level.floor.color, 0xFF202020);
+        assert_eq!(level.floor.texture.as_deref(), Some("stone"));
+        assert_eq!((level.spawn.x, level.spawn.y), (1.5 * tile, 2.5 * tile));
+        assert!((level.spawn.angle - PI).abs() < 1e-9);
+        assert_eq!(level.grid.get(0, 3), Some(TileId(1)));
+        assert_eq!(
+            level.sky,
+            Some(Sky {
+                texture: String::from("dusk"),
+                everywhere: true,
+            })
+        );
+        assert_eq!(
+            level.fog,
+            Some(Fog {
+                color: 0xFF808080,
+                start: 2.0 * tile,
+                end: 10.0 * tile,
+                falloff: Falloff::Exponential,
+            })
+        );
+        assert_eq!(level.entities.len(), 2);
+        assert_eq!(level.entities[0].kind, "barrel");
+        assert_eq!(
+            (level.entities[0].x, level.entities[0].y),
+            (2.5 * tile, 1.5 * tile)
+        );
+        let lamp = &level.entities[1];
+        assert_eq!(lamp.name, "Lamp by the door");
+        assert!((lamp.angle - PI / 2.0).abs() < 1e-9);
+        assert_eq!(
+            lamp.properties.get("sprite").map(|s| s.as_str()),
+            Some("lamp")
+        );
+    }
+
+    #[test]
+    fn test_fog() {
+        let linear = Fog::new(0xFF000000, 10.0, 20.0, Falloff::Linear).unwrap();
+        assert_eq!(linear.amount(5.0), 0.0);
+        assert_eq!(linear.amount(15.0), 0.5);
+        assert_eq!(linear.amount(25.0), 1.0);
+        assert_eq!(linear.apply(0xFF804020, 15.0), 0xFF402010);
+
+        let exponential = Fog::new(0xFF000000, 10.0, 20.0, Falloff::Exponential).unwrap();
+        assert!(exponential.amount(15.0) > linear.amount(15.0));
+        assert!(exponential.amount(19.9) < 1.0);
+        assert!(Fog::new(0, 20.0, 10.0, Falloff::Linear).is_err());
+    }
+
+    #[test]
+    fn test_from_toml_defaults() {
+        let level = Level::from_toml(
+            "name = \"Bare\"\nmap = \"\"\"\n111\n1.1\n111\n\"\"\"\n",
+            &TileRegistry::default(),
+        )
+        .unwrap();
+        let tile = TILE_SIZE as f64;
+        assert_eq!(level.ceiling, Surface::color(DEFAULT_CEILING_COLOR));
+        assert_eq!(level.floor, Surface::color(DEFAULT_FLOOR_COLOR));
+        assert_eq!(level.par_time, None);
+        assert_eq!(level.sky, None);
+        assert_eq!(level.fog, None);
+        assert_eq!((level.spawn.x, level.spawn.y), (1.5 * tile, 1.5 * tile));
+    }
+
+    #[test]
+    fn test_from_toml_reports_map_errors_in_file_lines() {
+        let source = LEVEL.replace("1..1\n1111", "1.x1\n1111");
+        let err = Level::from_toml(&source, &TileRegistry::default()).unwrap_err();
+        assert_eq!(err, "line 9, column 3: unknown tile 'x'");
+    }
+
+    #[test]
+    fn test_from_toml_rejects_bad_colors_and_fields() {
+        let tiles = TileRegistry::default();
+        let bad_color = LEVEL.replace("#101010", "grey");
+        assert!(Level::from_toml(&bad_color, &tiles)
+            .unwrap_err()
+            .contains("ceiling"));
+        let bad_fog = LEVEL.replace("end = 10", "end = 1");
+        assert!(Level::from_toml(&bad_fog, &tiles)
+            .unwrap_err()
+            .starts_with("fog"));
+        let unknown = LEVEL.replace("par_time", "par");
+        assert!(Level::from_toml(&unknown, &tiles).is_err());
+    }
+
+    #[test]
+    fn test_portals() {
+        let tiles = TileRegistry::default();
+        let source = |portal: &str| {
+            format!(
+                "name = \"Portals\"\nmap = \"\"\"\n1111111\n1O...O1\n1111111\n\"\"\"\n{}",
+                portal
+            )
+        };
+        let level = Level::from_toml(
+            &source("[[portal]]\na = [1, 1]\nb = [5, 1]\nturn = -90\n"),
+            &tiles,
+        )
+        .unwrap();
+        assert_eq!(
+            level.portals,
+            vec![Portal {
+                a: (1, 1),
+                b: (5, 1),
+                turns: 3
+            }]
+        );
+
+        let not_a_portal = source("[[portal]]\na = [1, 1]\nb = [4, 1]\n");
+        assert_eq!(
+            Level::from_toml(&not_a_portal, &tiles).unwrap_err(),
+            "portal 1: cell (4, 1) is not a portal tile"
+        );
+        let itself = source("[[portal]]\na = [1, 1]\nb = [1, 1]\n");
+        assert!(Level::from_toml(&itself, &tiles).is_err());
+        let bad_turn = source("[[portal]]\na = [1, 1]\nb = [5, 1]\nturn = 45\n");
+        assert!(Level::from_toml(&bad_turn, &tiles)
+            .unwrap_err()
+            .contains("multiple of 90"));
+    }
+
+    #[test]
+    fn test_default_level() {
+        let level = Level::default();
+        assert_eq!((level.grid.width(), level.grid.height()), (20, 13));
+        assert_eq!(level.ceiling, Surface::color(DEFAULT_CEILING_COLOR));
+        assert!(level.has_exit(&TileRegistry::default()));
+    }
+}
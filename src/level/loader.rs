@@ -0,0 +1,74 @@
+use crate::{
+    map::validate::{validate, Diagnostic},
+    tile::registry::TileRegistry,
+};
+
+use super::{
+    level::{Level, DEFAULT_LEVEL},
+    wolf3d,
+};
+
+// load_level reads the level at path, or the built in level when there is none,
+// and validates it. map_number picks the map of a Wolfenstein 3D archive.
+// Warnings are printed and errors fail the load.
+pub fn load_level(
+    path: Option<&str>,
+    map_number: usize,
+    tiles: &TileRegistry,
+) -> Result<Level, String> {
+    let level = match path {
+        Some(path) if wolf3d::is_gamemaps(path) => wolf3d::load_file(path, map_number, tiles)?,
+        Some(path) => Level::from_file(path, tiles)?,
+        None => Level::from_toml(DEFAULT_LEVEL, tiles)?,
+    };
+    check_level(level, path.unwrap_or("default level"), tiles)
+}
+
+// check_level validates a level, printing its warnings.
+// source names the level in messages.
+pub fn check_level(level: Level, source: &str, tiles: &TileRegistry) -> Result<Level, String> {
+    let (errors, warnings): (Vec<Diagnostic>, Vec<Diagnostic>) = validate(
+        &level.grid,
+        &level.spawn,
+        &level.portals,
+        &level.entities,
+        tiles,
+    )
+    .into_iter()
+    .partition(|d| d.is_error());
+    for warning in warnings {
+        eprintln!("{}: warning: {}", source, warning);
+    }
+    if !errors.is_empty() {
+        let messages: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        return Err(format!(
+            "{}: invalid level\n  {}",
+            source,
+            messages.join("\n  ")
+        ));
+    }
+    Ok(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_default_level() {
+        let level = load_level(None, 0, &TileRegistry::default()).unwrap();
+        assert_eq!(level.name, "Entrance");
+    }
+
+    #[test]
+    fn test_load_level_reports_errors() {
+        let dir =
+            std::env::temp_dir().join(format!("raycasting_open_level_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("open.map");
+        std::fs::write(&path, "1.11\n1..1\n1111\n").unwrap();
+        let err = load_level(path.to_str(), 0, &TileRegistry::default()).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(err.contains("cell (1, 0): outer boundary is open"));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod level;
+pub mod loader;
+pub mod portal;
+pub mod tiled;
+pub mod wolf3d;
@@ -0,0 +1,115 @@
+use std::f64::consts::FRAC_PI_2;
+
+use crate::window::window::TILE_SIZE;
+
+// Portal joins the portal tiles of two cells. Whatever enters one of them
+// comes out of the other as if both cells were the same one, turned by
+// turns quarter turns clockwise on the way from a to b.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Portal {
+    pub a: (usize, usize),
+    pub b: (usize, usize),
+    pub turns: u32,
+}
+
+impl Portal {
+    // other_side returns the cell joined to cell and the quarter turns taken
+    // going there, None when the portal does not lead from cell
+    pub fn other_side(&self, cell: (usize, usize)) -> Option<((usize, usize), u32)> {
+        if cell == self.a {
+            Some((self.b, self.turns % 4))
+        } else if cell == self.b {
+            Some((self.a, (4 - self.turns % 4) % 4))
+        } else {
+            None
+        }
+    }
+
+    // warp carries the point x, y of cell and the heading angle through the
+    // portal, the point keeps its place relative to the centre of the cell
+    pub fn warp(
+        &self,
+        cell: (usize, usize),
+        x: f64,
+        y: f64,
+        angle: f64,
+    ) -> Option<(f64, f64, f64)> {
+        let (to, turns) = self.other_side(cell)?;
+        let tile = TILE_SIZE as f64;
+        let center =
+            |(cx, cy): (usize, usize)| ((cx as f64 + 0.5) * tile, (cy as f64 + 0.5) * tile);
+        let (from_x, from_y) = center(cell);
+        let (to_x, to_y) = center(to);
+        // quarter turns are done exactly, y grows downwards so they are clockwise
+        let (dx, dy) = (x - from_x, y - from_y);
+        let (dx, dy) = match turns {
+            0 => (dx, dy),
+            1 => (-dy, dx),
+            2 => (-dx, -dy),
+            _ => (dy, -dx),
+        };
+        Some((to_x + dx, to_y + dy, angle + turns as f64 * FRAC_PI_2))
+    }
+}
+
+// leave_cell returns how far from x, y along angle the border of the cell
+// containing x, y is crossed
+pub fn leave_cell(x: f64, y: f64, angle: f64) -> f64 {
+    let tile = TILE_SIZE as f64;
+    let (sin, cos) = angle.sin_cos();
+    let mut distance = f64::INFINITY;
+    for (start, direction) in [(x, cos), (y, sin)] {
+        if direction.abs() < f64::EPSILON {
+            continue;
+        }
+        let low = (start / tile).floor() * tile;
+        let border = match direction > 0.0 {
+            true => low + tile,
+            false => low,
+        };
+        distance = distance.min((border - start) / direction);
+    }
+    distance
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    #[test]
+    fn test_warp() {
+        let tile = TILE_SIZE as f64;
+        let portal = Portal {
+            a: (2, 1),
+            b: (6, 4),
+            turns: 1,
+        };
+        assert_eq!(portal.other_side((2, 1)), Some(((6, 4), 1)));
+        assert_eq!(portal.other_side((6, 4)), Some(((2, 1), 3)));
+        assert_eq!(portal.other_side((1, 1)), None);
+
+        // entering a from the west heading east leaves b heading south,
+        // from the middle of its north side
+        let (x, y, angle) = portal.warp((2, 1), 2.0 * tile, 1.5 * tile, 0.0).unwrap();
+        assert_eq!((x, y), (6.5 * tile, 4.0 * tile));
+        assert!((angle - PI / 2.0).abs() < 1e-9);
+
+        // and the way back undoes the turn
+        let (x, y, angle) = portal.warp((6, 4), x, y, angle).unwrap();
+        assert_eq!((x, y), (2.0 * tile, 1.5 * tile));
+        assert!((angle - 2.0 * PI).abs() < 1e-9);
+        assert_eq!(portal.warp((0, 0), 0.0, 0.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_leave_cell() {
+        let tile = TILE_SIZE as f64;
+        assert_eq!(leave_cell(0.25 * tile, 0.5 * tile, 0.0), 0.75 * tile);
+        assert_eq!(leave_cell(1.25 * tile, 0.5 * tile, PI), 0.25 * tile);
+        assert!(
+            (leave_cell(0.5 * tile, 0.5 * tile, PI / 4.0) - 0.5 * tile * 2f64.sqrt()).abs() < 1e-9
+        );
+    }
+}
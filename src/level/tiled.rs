@@ -0,0 +1,705 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    map::grid::Grid,
+    tile::{registry::TileRegistry, tile::TileId},
+    utils::color::parse_color,
+    window::window::TILE_SIZE,
+};
+
+use super::level::{
+    Entity, Falloff, Fog, Level, Sky, Spawn, Surface, DEFAULT_CEILING_COLOR, DEFAULT_FLOOR_COLOR,
+    DEFAULT_FOG_COLOR,
+};
+
+// Tiled keeps the flip and rotation flags in the high bits of global tile ids
+const FLIP_FLAGS: u32 = 0xF0000000;
+
+// Objects of this type or name become the player spawn point
+const SPAWN_OBJECT: &str = "spawn";
+
+// TiledMap is the part of a Tiled map the engine understands,
+// read either from the XML (.tmx) or the JSON (.tmj) format
+struct TiledMap {
+    orientation: String,
+    infinite: bool,
+    width: usize,
+    height: usize,
+    tile_width: f64,
+    tile_height: f64,
+    properties: BTreeMap<String, String>,
+    tile_layers: Vec<TileLayer>,
+    objects: Vec<TiledObject>,
+}
+
+struct TileLayer {
+    name: String,
+    width: usize,
+    height: usize,
+    data: Vec<u32>,
+}
+
+struct TiledObject {
+    name: String,
+    kind: String,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    gid: Option<u32>,
+    properties: BTreeMap<String, String>,
+}
+
+impl TiledObject {
+    // center returns the middle of the object in Tiled pixels,
+    // tile objects are anchored at their bottom left corner
+    fn center(&self) -> (f64, f64) {
+        match self.gid {
+            Some(_) => (self.x + self.width / 2.0, self.y - self.height / 2.0),
+            None => (self.x + self.width / 2.0, self.y + self.height / 2.0),
+        }
+    }
+}
+
+// load_tmx reads a level from a Tiled XML map
+pub fn load_tmx(source: &str, name: String, tiles: &TileRegistry) -> Result<Level, String> {
+    into_level(parse_tmx(source)?, name, tiles)
+}
+
+// load_tmj reads a level from a Tiled JSON map
+pub fn load_tmj(source: &str, name: String, tiles: &TileRegistry) -> Result<Level, String> {
+    into_level(parse_tmj(source)?, name, tiles)
+}
+
+fn into_level(map: TiledMap, name: String, tiles: &TileRegistry) -> Result<Level, String> {
+    if map.orientation != "orthogonal" {
+        return Err(format!(
+            "{} maps are not supported, only orthogonal ones",
+            map.orientation
+        ));
+    }
+    if map.infinite {
+        return Err(String::from("infinite maps are not supported"));
+    }
+    if map.tile_width != map.tile_height || map.tile_width <= 0.0 {
+        return Err(format!(
+            "tiles must be square, found {}x{}",
+            map.tile_width, map.tile_height
+        ));
+    }
+
+    let mut layers = map.tile_layers.into_iter();
+    let layer = layers
+        .next()
+        .ok_or_else(|| String::from("map has no tile layer"))?;
+    if let Some(extra) = layers.next() {
+        return Err(format!(
+            "only one tile layer is supported, found '{}' and '{}'",
+            layer.name, extra.name
+        ));
+    }
+    if layer.width != map.width || layer.height != map.height {
+        return Err(format!(
+            "layer '{}' is {}x{} but the map is {}x{}",
+            layer.name, layer.width, layer.height, map.width, map.height
+        ));
+    }
+    if layer.data.len() != layer.width * layer.height {
+        return Err(format!(
+            "layer '{}' holds {} tiles, expected {}",
+            layer.name,
+            layer.data.len(),
+            layer.width * layer.height
+        ));
+    }
+
+    // global tile ids are used as engine tile ids, 0 being empty
+    let rows = layer
+        .data
+        .chunks(layer.width.max(1))
+        .map(|row| {
+            row.iter()
+                .map(|gid| {
+                    u16::try_from(gid & !FLIP_FLAGS)
+                        .map(TileId)
+                        .map_err(|_| format!("tile id {} is out of range", gid & !FLIP_FLAGS))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let grid =
+        Grid::from_rows(rows).ok_or_else(|| String::from("tile layer is not rectangular"))?;
+
+    // object positions are in Tiled pixels
+    let scale = TILE_SIZE as f64 / map.tile_width;
+    let mut spawn = None;
+    let mut entities = Vec::new();
+    for object in map.objects {
+        let (x, y) = object.center();
+        let angle = match object.properties.get("angle") {
+            Some(angle) => angle
+                .parse::<f64>()
+                .map_err(|_| format!("object '{}' has an invalid angle {}", object.name, angle))?
+                .to_radians(),
+            None => 0.0,
+        };
+
+        if object.kind.eq_ignore_ascii_case(SPAWN_OBJECT)
+            || object.name.eq_ignore_ascii_case(SPAWN_OBJECT)
+        {
+            if spawn.is_some() {
+                return Err(String::from("map has more than one spawn object"));
+            }
+            spawn = Some(Spawn {
+                x: x * scale,
+                y: y * scale,
+                angle,
+            });
+        } else {
+            entities.push(Entity {
+                kind: object.kind,
+                name: object.name,
+                x: x * scale,
+                y: y * scale,
+                angle,
+                properties: object.properties,
+            });
+        }
+    }
+
+    let properties = map.properties;
+    let surface = |key: &str, default_color: u32| -> Result<Surface, String> {
+        let color = match properties.get(key) {
+            Some(color) => parse_color(color).map_err(|err| format!("{}: {}", key, err))?,
+            None => default_color,
+        };
+        let texture = properties.get(&format!("{}_texture", key)).cloned();
+        Ok(Surface { color, texture })
+    };
+    let par_time = match properties.get("par_time") {
+        Some(par) => Some(
+            par.parse::<f64>()
+                .map_err(|_| format!("invalid par_time {}", par))?,
+        ),
+        None => None,
+    };
+
+    let everywhere = properties
+        .get("sky_everywhere")
+        .is_some_and(|value| value == "true");
+    let sky = match properties.get("sky") {
+        Some(texture) => Some(Sky {
+            texture: texture.clone(),
+            everywhere,
+        }),
+        None if everywhere => Some(Sky {
+            everywhere,
+            ..Sky::default()
+        }),
+        None => None,
+    };
+
+    // fog is turned on by its end distance, distances are in tiles
+    let number = |key: &str, default: f64| match properties.get(key) {
+        Some(value) => value
+            .parse::<f64>()
+            .map_err(|_| format!("invalid {} {}", key, value)),
+        None => Ok(default),
+    };
+    let fog = match properties.get("fog_end") {
+        Some(_) => {
+            let tile = TILE_SIZE as f64;
+            let color = match properties.get("fog_color") {
+                Some(color) => parse_color(color).map_err(|err| format!("fog_color: {}", err))?,
+                None => DEFAULT_FOG_COLOR,
+            };
+            let falloff = match properties.get("fog_falloff").map(|f| f.as_str()) {
+                None | Some("linear") => Falloff::Linear,
+                Some("exponential") => Falloff::Exponential,
+                Some(other) => return Err(format!("invalid fog_falloff {}", other)),
+            };
+            Some(Fog::new(
+                color,
+                number("fog_start", 0.0)? * tile,
+                number("fog_end", 0.0)? * tile,
+                falloff,
+            )?)
+        }
+        None => None,
+    };
+
+    Ok(Level {
+        name: properties.get("name").cloned().unwrap_or(name),
+        spawn: spawn.unwrap_or_else(|| Level::default_spawn(&grid, tiles)),
+        grid,
+        ceiling: surface("ceiling", DEFAULT_CEILING_COLOR)?,
+        floor: surface("floor", DEFAULT_FLOOR_COLOR)?,
+        sky,
+        fog,
+        par_time,
+        entities,
+        portals: Vec::new(),
+    })
+}
+
+fn parse_tile_data(
+    text: &str,
+    encoding: Option<&str>,
+    compression: Option<&str>,
+) -> Result<Vec<u32>, String> {
+    if let Some(compression) = compression.filter(|c| !c.is_empty()) {
+        return Err(format!(
+            "{} compressed tile data is not supported, save the map with CSV or uncompressed Base64",
+            compression
+        ));
+    }
+
+    match encoding {
+        Some("csv") => text
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid tile id {}", value))
+            })
+            .collect(),
+        Some("base64") => {
+            let bytes = decode_base64(text.trim())?;
+            if bytes.len() % 4 != 0 {
+                return Err(String::from("Base64 tile data is not a list of 32 bit ids"));
+            }
+            Ok(bytes
+                .chunks(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect())
+        }
+        Some(encoding) => Err(format!("{} tile data encoding is not supported", encoding)),
+        None => Err(String::from(
+            "XML tile data is not supported, save the map with CSV encoding",
+        )),
+    }
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let value = |c: u8| -> Result<u32, String> {
+        match c {
+            b'A'..=b'Z' => Ok((c - b'A') as u32),
+            b'a'..=b'z' => Ok((c - b'a' + 26) as u32),
+            b'0'..=b'9' => Ok((c - b'0' + 52) as u32),
+            b'+' => Ok(62),
+            b'/' => Ok(63),
+            _ => Err(format!("invalid Base64 character '{}'", c as char)),
+        }
+    };
+
+    let text: Vec<u8> = text
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace() && *c != b'=')
+        .collect();
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    for chunk in text.chunks(4) {
+        let mut bits = 0;
+        for (i, c) in chunk.iter().enumerate() {
+            bits |= value(*c)? << (18 - 6 * i);
+        }
+        let count = chunk.len() * 6 / 8;
+        bytes.extend(bits.to_be_bytes()[1..=count].iter());
+    }
+    Ok(bytes)
+}
+
+fn parse_tmx(source: &str) -> Result<TiledMap, String> {
+    let document = roxmltree::Document::parse(source).map_err(|err| err.to_string())?;
+    let root = document.root_element();
+    if !root.has_tag_name("map") {
+        return Err(format!(
+            "expected a <map> element, found <{}>",
+            root.tag_name().name()
+        ));
+    }
+
+    let mut map = TiledMap {
+        orientation: root
+            .attribute("orientation")
+            .unwrap_or("orthogonal")
+            .to_string(),
+        infinite: root.attribute("infinite") == Some("1"),
+        width: xml_number(root, "width")?,
+        height: xml_number(root, "height")?,
+        tile_width: xml_number(root, "tilewidth")?,
+        tile_height: xml_number(root, "tileheight")?,
+        properties: BTreeMap::new(),
+        tile_layers: Vec::new(),
+        objects: Vec::new(),
+    };
+
+    for node in root.children().filter(|node| node.is_element()) {
+        match node.tag_name().name() {
+            "properties" => map.properties = xml_properties(node),
+            "layer" => {
+                let data = node
+                    .children()
+                    .find(|child| child.has_tag_name("data"))
+                    .ok_or_else(|| String::from("tile layer has no data"))?;
+                if data.children().any(|child| child.has_tag_name("chunk")) {
+                    return Err(String::from("infinite maps are not supported"));
+                }
+                map.tile_layers.push(TileLayer {
+                    name: node.attribute("name").unwrap_or_default().to_string(),
+                    width: xml_number(node, "width")?,
+                    height: xml_number(node, "height")?,
+                    data: parse_tile_data(
+                        data.text().unwrap_or_default(),
+                        data.attribute("encoding"),
+                        data.attribute("compression"),
+                    )?,
+                });
+            }
+            "objectgroup" => {
+                for object in node.children().filter(|child| child.has_tag_name("object")) {
+                    map.objects.push(TiledObject {
+                        name: object.attribute("name").unwrap_or_default().to_string(),
+                        kind: object
+                            .attribute("type")
+                            .or_else(|| object.attribute("class"))
+                            .unwrap_or_default()
+                            .to_string(),
+                        x: xml_number(object, "x")?,
+                        y: xml_number(object, "y")?,
+                        width: xml_optional_number(object, "width")?,
+                        height: xml_optional_number(object, "height")?,
+                        gid: object
+                            .attribute("gid")
+                            .map(|gid| gid.parse())
+                            .transpose()
+                            .map_err(|_| String::from("object has an invalid gid"))?,
+                        properties: object
+                            .children()
+                            .find(|child| child.has_tag_name("properties"))
+                            .map(xml_properties)
+                            .unwrap_or_default(),
+                    });
+                }
+            }
+            "group" => return Err(String::from("group layers are not supported")),
+            _ => {}
+        }
+    }
+    Ok(map)
+}
+
+fn xml_number<T: std::str::FromStr>(node: roxmltree::Node, name: &str) -> Result<T, String> {
+    let value = node
+        .attribute(name)
+        .ok_or_else(|| format!("<{}> is missing {}", node.tag_name().name(), name))?;
+    value.parse().map_err(|_| {
+        format!(
+            "<{}> has an invalid {} {}",
+            node.tag_name().name(),
+            name,
+            value
+        )
+    })
+}
+
+fn xml_optional_number(node: roxmltree::Node, name: &str) -> Result<f64, String> {
+    match node.attribute(name) {
+        Some(_) => xml_number(node, name),
+        None => Ok(0.0),
+    }
+}
+
+fn xml_properties(node: roxmltree::Node) -> BTreeMap<String, String> {
+    node.children()
+        .filter(|child| child.has_tag_name("property"))
+        .filter_map(|property| {
+            let value = property
+                .attribute("value")
+                .map(str::to_string)
+                .or_else(|| property.text().map(str::to_string))?;
+            Some((property.attribute("name")?.to_string(), value))
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct JsonMap {
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+    width: usize,
+    height: usize,
+    tilewidth: f64,
+    tileheight: f64,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+}
+
+#[derive(Deserialize)]
+struct JsonLayer {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    width: usize,
+    #[serde(default)]
+    height: usize,
+    data: Option<Value>,
+    encoding: Option<String>,
+    compression: Option<String>,
+    chunks: Option<Value>,
+    #[serde(default)]
+    objects: Vec<JsonObject>,
+}
+
+#[derive(Deserialize)]
+struct JsonObject {
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type")]
+    kind: String,
+    #[serde(default)]
+    class: String,
+    x: f64,
+    y: f64,
+    #[serde(default)]
+    width: f64,
+    #[serde(default)]
+    height: f64,
+    gid: Option<u32>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+struct JsonProperty {
+    name: String,
+    value: Value,
+}
+
+fn json_properties(properties: Vec<JsonProperty>) -> BTreeMap<String, String> {
+    properties
+        .into_iter()
+        .map(|property| {
+            let value = match property.value {
+                Value::String(value) => value,
+                value => value.to_string(),
+            };
+            (property.name, value)
+        })
+        .collect()
+}
+
+fn parse_tmj(source: &str) -> Result<TiledMap, String> {
+    let raw: JsonMap = serde_json::from_str(source).map_err(|err| err.to_string())?;
+    let mut map = TiledMap {
+        orientation: raw.orientation,
+        infinite: raw.infinite,
+        width: raw.width,
+        height: raw.height,
+        tile_width: raw.tilewidth,
+        tile_height: raw.tileheight,
+        properties: json_properties(raw.properties),
+        tile_layers: Vec::new(),
+        objects: Vec::new(),
+    };
+
+    for layer in raw.layers {
+        match layer.kind.as_str() {
+            "tilelayer" => {
+                if layer.chunks.is_some() {
+                    return Err(String::from("infinite maps are not supported"));
+                }
+                let data = match layer.data {
+                    Some(Value::Array(values)) => values
+                        .iter()
+                        .map(|value| {
+                            value
+                                .as_u64()
+                                .and_then(|gid| u32::try_from(gid).ok())
+                                .ok_or_else(|| format!("invalid tile id {}", value))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?,
+                    Some(Value::String(text)) => parse_tile_data(
+                        &text,
+                        layer.encoding.as_deref(),
+                        layer.compression.as_deref(),
+                    )?,
+                    _ => return Err(format!("tile layer '{}' has no data", layer.name)),
+                };
+                map.tile_layers.push(TileLayer {
+                    name: layer.name,
+                    width: layer.width,
+                    height: layer.height,
+                    data,
+                });
+            }
+            "objectgroup" => {
+                for object in layer.objects {
+                    map.objects.push(TiledObject {
+                        name: object.name,
+                        kind: if object.kind.is_empty() {
+                            object.class
+                        } else {
+                            object.kind
+                        },
+                        x: object.x,
+                        y: object.y,
+                        width: object.width,
+                        height: object.height,
+                        gid: object.gid,
+                        properties: json_properties(object.properties),
+                    });
+                }
+            }
+            "group" => return Err(String::from("group layers are not supported")),
+            _ => {}
+        }
+    }
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TMX: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" renderorder="right-down" width="4" height="3" tilewidth="32" tileheight="32" infinite="0">
+ <properties>
+  <property name="name" value="Courtyard"/>
+  <property name="par_time" type="float" value="30"/>
+  <property name="floor" type="color" value="#ff203040"/>
+ </properties>
+ <tileset firstgid="1" source="walls.tsx"/>
+ <layer id="1" name="walls" width="4" height="3">
+  <data encoding="csv">
+1,1,1,1,
+1,0,0,2147483651,
+1,1,1,1
+</data>
+ </layer>
+ <objectgroup id="2" name="objects">
+  <object id="1" name="start" type="spawn" x="48" y="48">
+   <properties>
+    <property name="angle" type="float" value="180"/>
+   </properties>
+   <point/>
+  </object>
+  <object id="2" name="barrel" class="prop" gid="5" x="64" y="64" width="32" height="32"/>
+ </objectgroup>
+</map>
+"##;
+
+    const TMJ: &str = r##"{
+ "orientation": "orthogonal", "infinite": false,
+ "width": 3, "height": 3, "tilewidth": 64, "tileheight": 64,
+ "properties": [
+  { "name": "ceiling_texture", "type": "string", "value": "wood" },
+  { "name": "sky_everywhere", "type": "bool", "value": true },
+  { "name": "fog_end", "type": "float", "value": 8 },
+  { "name": "fog_falloff", "type": "string", "value": "exponential" }
+ ],
+ "layers": [
+  { "type": "tilelayer", "name": "walls", "width": 3, "height": 3,
+    "data": [1, 1, 1, 1, 0, 1, 1, 1, 1] },
+  { "type": "objectgroup", "name": "objects", "objects": [
+    { "name": "spawn", "type": "", "x": 96, "y": 96, "width": 0, "height": 0 },
+    { "name": "lamp", "type": "light", "x": 64, "y": 64, "width": 64, "height": 64,
+      "properties": [{ "name": "radius", "type": "int", "value": 3 }] }
+  ] }
+ ]
+}"##;
+
+    #[test]
+    fn test_load_tmx() {
+        let level = load_tmx(TMX, String::from("courtyard"), &TileRegistry::default()).unwrap();
+        let tile = TILE_SIZE as f64;
+        assert_eq!(level.name, "Courtyard");
+        assert_eq!(level.par_time, Some(30.0));
+        assert_eq!(level.floor.color, 0xFF203040);
+        assert_eq!((level.grid.width(), level.grid.height()), (4, 3));
+        assert_eq!(level.grid.get(1, 1), Some(TileId::EMPTY));
+        assert_eq!(level.grid.get(3, 1), Some(TileId(3)));
+        assert_eq!((level.spawn.x, level.spawn.y), (1.5 * tile, 1.5 * tile));
+        assert!((level.spawn.angle - std::f64::consts::PI).abs() < 1e-9);
+
+        assert_eq!(level.entities.len(), 1);
+        let barrel = &level.entities[0];
+        assert_eq!(
+            (barrel.kind.as_str(), barrel.name.as_str()),
+            ("prop", "barrel")
+        );
+        assert_eq!((barrel.x, barrel.y), (2.5 * tile, 1.5 * tile));
+    }
+
+    #[test]
+    fn test_load_tmj() {
+        let level = load_tmj(TMJ, String::from("room"), &TileRegistry::default()).unwrap();
+        let tile = TILE_SIZE as f64;
+        assert_eq!(level.name, "room");
+        assert_eq!(level.ceiling.texture.as_deref(), Some("wood"));
+        assert_eq!(
+            level.sky,
+            Some(Sky {
+                everywhere: true,
+                ..Sky::default()
+            })
+        );
+        let fog = level.fog.unwrap();
+        assert_eq!((fog.start, fog.end), (0.0, 8.0 * TILE_SIZE as f64));
+        assert_eq!(fog.falloff, Falloff::Exponential);
+        assert_eq!((level.spawn.x, level.spawn.y), (1.5 * tile, 1.5 * tile));
+        assert_eq!(level.entities[0].kind, "light");
+        assert_eq!(level.entities[0].properties.get("radius").unwrap(), "3");
+        assert_eq!(
+            (level.entities[0].x, level.entities[0].y),
+            (1.5 * tile, 1.5 * tile)
+        );
+    }
+
+    #[test]
+    fn test_base64_tile_data() {
+        // ids 1, 0, 2 as little endian 32 bit values
+        let data = parse_tile_data(" AQAAAAAAAAACAAAA ", Some("base64"), None).unwrap();
+        assert_eq!(data, vec![1, 0, 2]);
+        assert!(parse_tile_data("AQAAAA==", Some("base64"), Some("zlib")).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_features() {
+        let tiles = TileRegistry::default();
+        let name = || String::from("bad");
+
+        let isometric = TMX.replace("orthogonal", "isometric");
+        assert!(load_tmx(&isometric, name(), &tiles)
+            .unwrap_err()
+            .contains("isometric"));
+
+        let infinite = TMJ.replace("\"infinite\": false", "\"infinite\": true");
+        assert!(load_tmj(&infinite, name(), &tiles)
+            .unwrap_err()
+            .contains("infinite"));
+
+        let compressed = TMX.replace(
+            "<data encoding=\"csv\">",
+            "<data encoding=\"base64\" compression=\"zlib\">",
+        );
+        assert!(load_tmx(&compressed, name(), &tiles)
+            .unwrap_err()
+            .contains("zlib"));
+
+        let two_spawns = TMJ.replace(
+            "\"name\": \"lamp\", \"type\": \"light\"",
+            "\"name\": \"lamp\", \"type\": \"spawn\"",
+        );
+        assert!(load_tmj(&two_spawns, name(), &tiles).is_err());
+    }
+}
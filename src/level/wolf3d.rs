@@ -0,0 +1,488 @@
+use std::{
+    collections::BTreeMap,
+    f64::consts::PI,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    map::grid::Grid,
+    sprite::sprite::SPRITE_PROPERTY,
+    tile::{registry::TileRegistry, tile::TileId},
+    window::window::TILE_SIZE,
+};
+
+use super::level::{Entity, Level, Spawn, Surface, DEFAULT_CEILING_COLOR, DEFAULT_FLOOR_COLOR};
+
+// Compression tags of the Carmack scheme, stored in the high byte of a word
+const NEAR_TAG: u16 = 0xA7;
+const FAR_TAG: u16 = 0xA8;
+
+// MAPHEAD holds the RLEW tag followed by the offset of every map header
+const MAX_MAPS: usize = 100;
+
+// Wall plane values up to this one are walls, the values
+// above mark doors and floor areas
+const LAST_WALL: u16 = 63;
+
+// Wall plane values of doors, floor areas are left empty
+const FIRST_DOOR: u16 = 90;
+const LAST_DOOR: u16 = 101;
+
+// Object plane value marking the wall under it as a push wall,
+// which becomes the secret tile SECRET_TILES above the wall
+const PUSH_WALL: u16 = 98;
+const SECRET_TILES: u16 = 128;
+
+// Object plane values of the player start, one per facing
+const PLAYER_NORTH: u16 = 19;
+const PLAYER_WEST: u16 = 22;
+
+// Static objects that look like one of the built in sprites:
+// the green and brown barrels, the floor lamp and the white pillar
+const STATIC_SPRITES: [(u16, &str); 4] =
+    [(24, "barrel"), (26, "lamp"), (30, "pillar"), (58, "barrel")];
+
+// MapHeader describes one map of a GAMEMAPS archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapHeader {
+    pub plane_offsets: [u32; 3],
+    pub plane_lengths: [u16; 3],
+    pub width: u16,
+    pub height: u16,
+    pub name: String,
+}
+
+// Wolf3dMap is a decoded map with its wall and object planes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wolf3dMap {
+    pub header: MapHeader,
+    pub walls: Vec<u16>,
+    pub objects: Vec<u16>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], position: usize) -> Self {
+        Reader { data, position }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or_else(|| format!("unexpected end of data at byte {}", self.position))?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+// parse_maphead returns the RLEW tag and the header offset of every map,
+// missing maps have no offset
+pub fn parse_maphead(data: &[u8]) -> Result<(u16, Vec<Option<u32>>), String> {
+    let mut reader = Reader::new(data, 0);
+    let rlew_tag = reader.u16().map_err(|err| format!("MAPHEAD: {}", err))?;
+
+    let mut offsets = Vec::with_capacity(MAX_MAPS);
+    for _ in 0..MAX_MAPS {
+        let offset = match reader.u32() {
+            Ok(offset) => offset,
+            Err(_) => break,
+        };
+        offsets.push(match offset {
+            0 | 0xFFFFFFFF => None,
+            offset => Some(offset),
+        });
+    }
+    Ok((rlew_tag, offsets))
+}
+
+pub fn parse_map_header(gamemaps: &[u8], offset: u32) -> Result<MapHeader, String> {
+    let mut reader = Reader::new(gamemaps, offset as usize);
+    let mut plane_offsets = [0; 3];
+    for plane_offset in plane_offsets.iter_mut() {
+        *plane_offset = reader.u32()?;
+    }
+    let mut plane_lengths = [0; 3];
+    for plane_length in plane_lengths.iter_mut() {
+        *plane_length = reader.u16()?;
+    }
+    let width = reader.u16()?;
+    let height = reader.u16()?;
+    let name = reader.bytes(16)?;
+    let name = String::from_utf8_lossy(name.split(|b| *b == 0).next().unwrap_or_default());
+
+    Ok(MapHeader {
+        plane_offsets,
+        plane_lengths,
+        width,
+        height,
+        name: name.trim().to_string(),
+    })
+}
+
+// carmack_expand undoes the Carmack compression, the data starts
+// with the expanded length in bytes followed by words which are either
+// literals or near and far pointers back into the expanded output
+pub fn carmack_expand(data: &[u8]) -> Result<Vec<u16>, String> {
+    let mut reader = Reader::new(data, 0);
+    let length = reader.u16()? as usize / 2;
+    let mut words: Vec<u16> = Vec::with_capacity(length);
+
+    while words.len() < length {
+        let word = reader.u16()?;
+        let tag = word >> 8;
+        let count = (word & 0xFF) as usize;
+
+        let start = match tag {
+            // a zero count escapes a literal whose high byte equals a tag
+            NEAR_TAG | FAR_TAG if count == 0 => {
+                words.push((word & 0xFF00) | reader.u8()? as u16);
+                continue;
+            }
+            NEAR_TAG => {
+                let offset = reader.u8()? as usize;
+                words
+                    .len()
+                    .checked_sub(offset)
+                    .filter(|_| offset > 0)
+                    .ok_or_else(|| format!("near pointer {} before start of data", offset))?
+            }
+            FAR_TAG => reader.u16()? as usize,
+            _ => {
+                words.push(word);
+                continue;
+            }
+        };
+
+        for i in start..start + count {
+            let copied = *words
+                .get(i)
+                .ok_or_else(|| format!("pointer to word {} past end of data", i))?;
+            words.push(copied);
+        }
+    }
+
+    words.truncate(length);
+    Ok(words)
+}
+
+// rlew_expand undoes the run length encoding, the first word is the
+// expanded length in bytes and every tag is followed by a count and a value
+pub fn rlew_expand(words: &[u16], rlew_tag: u16) -> Result<Vec<u16>, String> {
+    let mut words = words.iter().copied();
+    let length = words
+        .next()
+        .ok_or_else(|| String::from("RLEW data is empty"))? as usize
+        / 2;
+    let mut expanded = Vec::with_capacity(length);
+
+    while expanded.len() < length {
+        match words.next() {
+            Some(word) if word == rlew_tag => {
+                let (count, value) = match (words.next(), words.next()) {
+                    (Some(count), Some(value)) => (count, value),
+                    _ => return Err(String::from("RLEW run is cut short")),
+                };
+                expanded.extend(std::iter::repeat_n(value, count as usize));
+            }
+            Some(word) => expanded.push(word),
+            None => return Err(String::from("RLEW data is cut short")),
+        }
+    }
+
+    expanded.truncate(length);
+    Ok(expanded)
+}
+
+// read_map decodes the wall and object planes of map number index
+pub fn read_map(maphead: &[u8], gamemaps: &[u8], index: usize) -> Result<Wolf3dMap, String> {
+    let (rlew_tag, offsets) = parse_maphead(maphead)?;
+    let offset = offsets
+        .get(index)
+        .copied()
+        .flatten()
+        .ok_or_else(|| format!("map {} is not in the archive", index))?;
+    let header =
+        parse_map_header(gamemaps, offset).map_err(|err| format!("map {}: {}", index, err))?;
+
+    let size = header.width as usize * header.height as usize;
+    let plane = |plane: usize| -> Result<Vec<u16>, String> {
+        let start = header.plane_offsets[plane] as usize;
+        let data = gamemaps
+            .get(start..start + header.plane_lengths[plane] as usize)
+            .ok_or_else(|| format!("plane {} lies outside of GAMEMAPS", plane))?;
+        let words = rlew_expand(&carmack_expand(data)?, rlew_tag)?;
+        if words.len() != size {
+            return Err(format!(
+                "plane {} holds {} tiles, expected {}",
+                plane,
+                words.len(),
+                size
+            ));
+        }
+        Ok(words)
+    };
+
+    Ok(Wolf3dMap {
+        walls: plane(0).map_err(|err| format!("map {}: {}", index, err))?,
+        objects: plane(1).map_err(|err| format!("map {}: {}", index, err))?,
+        header,
+    })
+}
+
+// wall_tile maps a wall plane value onto an engine tile id,
+// walls and doors keep their number so a matching tile set can be used
+pub fn wall_tile(value: u16) -> TileId {
+    match value {
+        1..=LAST_WALL | FIRST_DOOR..=LAST_DOOR => TileId(value),
+        _ => TileId::EMPTY,
+    }
+}
+
+impl Wolf3dMap {
+    pub fn into_level(self, tiles: &TileRegistry) -> Level {
+        let width = self.header.width as usize;
+        let rows = self
+            .walls
+            .chunks(width.max(1))
+            .map(|row| row.iter().map(|value| wall_tile(*value)).collect())
+            .collect();
+        let mut grid = Grid::from_rows(rows).expect("planes are checked to match the map size");
+
+        let tile = TILE_SIZE as f64;
+        let mut spawn = None;
+        let mut entities = Vec::new();
+        for (i, value) in self.objects.iter().enumerate() {
+            let x = ((i % width) as f64 + 0.5) * tile;
+            let y = ((i / width) as f64 + 0.5) * tile;
+            match *value {
+                0 => {}
+                PUSH_WALL => {
+                    if let wall @ 1..=LAST_WALL = self.walls[i] {
+                        grid.set(i % width, i / width, TileId(wall + SECRET_TILES));
+                    }
+                }
+                PLAYER_NORTH..=PLAYER_WEST => {
+                    // starts are ordered north, east, south, west
+                    let angle = (*value - PLAYER_NORTH) as f64 * PI / 2.0 - PI / 2.0;
+                    spawn = Some(Spawn {
+                        x,
+                        y,
+                        angle: angle.rem_euclid(2.0 * PI),
+                    });
+                }
+                value => {
+                    let mut properties =
+                        BTreeMap::from([(String::from("object"), value.to_string())]);
+                    if let Some((_, sprite)) = STATIC_SPRITES.iter().find(|(v, _)| *v == value) {
+                        properties.insert(String::from(SPRITE_PROPERTY), sprite.to_string());
+                    }
+                    entities.push(Entity {
+                        kind: String::from("wolf3d"),
+                        name: format!("object {}", value),
+                        x,
+                        y,
+                        angle: 0.0,
+                        properties,
+                    })
+                }
+            }
+        }
+
+        Level {
+            name: self.header.name,
+            spawn: spawn.unwrap_or_else(|| Level::default_spawn(&grid, tiles)),
+            grid,
+            ceiling: Surface::color(DEFAULT_CEILING_COLOR),
+            floor: Surface::color(DEFAULT_FLOOR_COLOR),
+            sky: None,
+            fog: None,
+            par_time: None,
+            entities,
+            portals: Vec::new(),
+        }
+    }
+}
+
+// is_gamemaps tells whether a path names a GAMEMAPS archive
+pub fn is_gamemaps(path: &str) -> bool {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.to_ascii_uppercase().starts_with("GAMEMAPS"))
+}
+
+// find_maphead returns the MAPHEAD file next to the GAMEMAPS archive at path,
+// named with the same extension in any case
+fn find_maphead(path: &Path, extension: &str) -> PathBuf {
+    let name = format!("MAPHEAD{}", extension);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.file_name())
+        .find(|file_name| {
+            file_name
+                .to_str()
+                .is_some_and(|file_name| file_name.eq_ignore_ascii_case(&name))
+        })
+        .map_or_else(
+            || path.with_file_name(&name),
+            |file_name| path.with_file_name(file_name),
+        )
+}
+
+// load_file reads map number index of a GAMEMAPS archive,
+// the MAPHEAD file is expected next to it with the same extension
+pub fn load_file(gamemaps_path: &str, index: usize, tiles: &TileRegistry) -> Result<Level, String> {
+    let path = Path::new(gamemaps_path);
+    let extension = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| is_gamemaps(name))
+        .and_then(|name| name.get("GAMEMAPS".len()..))
+        .ok_or_else(|| format!("invalid GAMEMAPS path {}", gamemaps_path))?;
+    let maphead_path = find_maphead(path, extension);
+
+    let read = |path: &Path| {
+        fs::read(path).map_err(|err| format!("failed to read {}: {}", path.display(), err))
+    };
+    let maphead = read(&maphead_path)?;
+    let gamemaps = read(path)?;
+
+    let map = read_map(&maphead, &gamemaps, index)
+        .map_err(|err| format!("{}: {}", gamemaps_path, err))?;
+    Ok(map.into_level(tiles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAPHEAD: &[u8] = include_bytes!("../../fixtures/wolf3d/MAPHEAD.TST");
+    const GAMEMAPS: &[u8] = include_bytes!("../../fixtures/wolf3d/GAMEMAPS.TST");
+
+    #[test]
+    fn test_parse_maphead() {
+        let (tag, offsets) = parse_maphead(MAPHEAD).unwrap();
+        assert_eq!(tag, 0xABCD);
+        assert_eq!(offsets.len(), MAX_MAPS);
+        assert!(offsets[0].is_some());
+        assert!(offsets[1..].iter().all(|offset| offset.is_none()));
+    }
+
+    #[test]
+    fn test_carmack_expand() {
+        // length, literal 1, literal 2, near copy of 2 words 2 back,
+        // escaped 0xA7FF literal and a far copy of 3 words from the start
+        let data = [14, 0, 1, 0, 2, 0, 2, 0xA7, 2, 0, 0xA7, 0xFF, 3, 0xA8, 0, 0];
+        assert_eq!(
+            carmack_expand(&data).unwrap(),
+            vec![1, 2, 1, 2, 0xA7FF, 1, 2]
+        );
+        assert!(carmack_expand(&[4, 0, 2, 0xA7, 1]).is_err());
+    }
+
+    #[test]
+    fn test_rlew_expand() {
+        let words = [12, 7, 0xABCD, 4, 9, 8];
+        assert_eq!(rlew_expand(&words, 0xABCD).unwrap(), vec![7, 9, 9, 9, 9, 8]);
+        assert!(rlew_expand(&[8, 0xABCD, 4], 0xABCD).is_err());
+    }
+
+    #[test]
+    fn test_read_map() {
+        let map = read_map(MAPHEAD, GAMEMAPS, 0).unwrap();
+        assert_eq!(map.header.name, "Test Room");
+        assert_eq!((map.header.width, map.header.height), (8, 6));
+        assert_eq!(&map.walls[..8], &[1; 8]);
+        assert_eq!(map.walls[2 * 8 + 3], 3);
+        assert_eq!(map.walls[4 * 8 + 7], 21);
+        assert_eq!(map.objects[8 + 2], PLAYER_NORTH);
+        assert!(read_map(MAPHEAD, GAMEMAPS, 1).is_err());
+        assert!(read_map(MAPHEAD, &GAMEMAPS[..40], 0).is_err());
+    }
+
+    #[test]
+    fn test_into_level() {
+        let level = read_map(MAPHEAD, GAMEMAPS, 0)
+            .unwrap()
+            .into_level(&TileRegistry::default());
+        let tile = TILE_SIZE as f64;
+
+        assert_eq!(level.name, "Test Room");
+        assert_eq!(level.grid.get(0, 0), Some(TileId(1)));
+        assert_eq!(level.grid.get(1, 1), Some(TileId::EMPTY));
+        assert_eq!(level.grid.get(3, 2), Some(TileId(3)));
+        assert_eq!(level.grid.get(7, 4), Some(TileId(21)));
+        // the push wall marker makes a secret of the wall under it
+        assert_eq!(level.grid.get(7, 3), Some(TileId(1 + SECRET_TILES)));
+        assert_eq!((level.spawn.x, level.spawn.y), (2.5 * tile, 1.5 * tile));
+        assert!((level.spawn.angle - 1.5 * PI).abs() < 1e-9);
+        assert_eq!(level.entities.len(), 2);
+        assert_eq!(level.entities[0].properties["object"], "23");
+        assert!(!level.entities[0].properties.contains_key(SPRITE_PROPERTY));
+    }
+
+    #[test]
+    fn test_static_objects_get_sprites() {
+        let mut map = read_map(MAPHEAD, GAMEMAPS, 0).unwrap();
+        let index = map.objects.iter().position(|value| *value == 23).unwrap();
+        map.objects[index] = 26;
+        let level = map.into_level(&TileRegistry::default());
+        assert_eq!(level.entities[0].properties[SPRITE_PROPERTY], "lamp");
+    }
+
+    #[test]
+    fn test_wall_tile() {
+        assert_eq!(wall_tile(1), TileId(1));
+        assert_eq!(wall_tile(LAST_WALL + 1), TileId::EMPTY);
+        assert_eq!(wall_tile(FIRST_DOOR), TileId(FIRST_DOOR));
+        assert_eq!(wall_tile(LAST_DOOR), TileId(LAST_DOOR));
+        assert_eq!(wall_tile(108), TileId::EMPTY);
+    }
+
+    #[test]
+    fn test_is_gamemaps() {
+        assert!(is_gamemaps("data/GAMEMAPS.WL6"));
+        assert!(is_gamemaps("gamemaps.sod"));
+        assert!(!is_gamemaps("maps/default.toml"));
+    }
+
+    #[test]
+    fn test_load_file_finds_maphead_in_any_case() {
+        let dir =
+            std::env::temp_dir().join(format!("raycasting_maphead_case_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("GameMaps.tst"), GAMEMAPS).unwrap();
+        fs::write(dir.join("mapHead.tst"), MAPHEAD).unwrap();
+
+        let path = dir.join("GameMaps.tst");
+        let level = load_file(path.to_str().unwrap(), 0, &TileRegistry::default());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(level.unwrap().name, "Test Room");
+    }
+}
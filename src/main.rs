@@ -1,22 +1,76 @@
-use crate::app::texture_owner::TextureOwner;
+use std::path::Path;
+
+use crate::{
+    app::texture_owner::TextureOwner,
+    campaign::campaign::Campaign,
+    game::game::Game,
+    generator::{generator::generate_level, rng::time_seed},
+    level::loader::{check_level, load_level},
+    options::options::Options,
+    sprite::actor::ActorRegistry,
+    texture::manager::TextureManager,
+    tile::registry::TileRegistry,
+};
 
 mod app;
+mod campaign;
 mod colorbuffer;
 mod game;
+mod generator;
+mod level;
+mod map;
+mod options;
 mod player;
 mod ray;
+mod sprite;
+mod texture;
+mod tile;
 mod timekeeper;
 mod utils;
 mod window;
 extern crate sdl2;
 
 pub fn main() -> Result<(), String> {
-    let mut owner = TextureOwner::new()?;
-    let mut app = owner.build_app()?;
+    let options = Options::parse(std::env::args().skip(1))?;
+    let tiles = match &options.tiles_path {
+        Some(path) => TileRegistry::from_file(path)?,
+        None => TileRegistry::default(),
+    };
+    let actors = match &options.actors_path {
+        Some(path) => ActorRegistry::from_file(path)?,
+        None => ActorRegistry::default(),
+    };
+    let mut campaign = match (&options.campaign_path, options.generate) {
+        (Some(path), _) => Campaign::from_file(path, &tiles)?,
+        (None, Some(algorithm)) => {
+            let seed = options.seed.unwrap_or_else(time_seed);
+            eprintln!("Generating a {} level with seed {}", algorithm, seed);
+            let level = generate_level(algorithm, options.size, seed, &tiles)?;
+            Campaign::single(check_level(level, "generated level", &tiles)?)
+        }
+        (None, None) => Campaign::single(load_level(
+            options.map_path.as_deref(),
+            options.map_number,
+            &tiles,
+        )?),
+    };
+    let mut textures = TextureManager::default();
+    let mut warnings = textures.load_dir(Path::new(&options.textures_dir))?;
+    warnings.extend(textures.load_tile_textures(&tiles));
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
+    }
+    let game = Game::from_level(campaign.current().clone(), tiles, actors);
+
+    let mut owner = TextureOwner::new(&options)?;
+    let mut app = owner.build_app(game, textures, options.resolution)?;
 
     while app.is_running {
         app.process_input();
         app.update();
+        if app.exit_reached() {
+            app.finish_level(&mut campaign)?;
+        }
         app.render()?;
     }
     Ok(())
@@ -0,0 +1,102 @@
+use crate::tile::tile::TileId;
+
+// Grid is a heap backed, row major map of tile ids
+// whose width and height are only known at runtime
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    cells: Vec<TileId>,
+}
+
+impl Grid {
+    // new builds a width by height grid with every cell set to fill
+    pub fn new(width: usize, height: usize, fill: TileId) -> Self {
+        Grid {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    // from_rows builds a grid from equally sized rows
+    // returning None when the rows are ragged
+    pub fn from_rows(rows: Vec<Vec<TileId>>) -> Option<Self> {
+        let height = rows.len();
+        let width = rows.first().map_or(0, |row| row.len());
+        if rows.iter().any(|row| row.len() != width) {
+            return None;
+        }
+
+        Some(Grid {
+            width,
+            height,
+            cells: rows.into_iter().flatten().collect(),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<TileId> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.cells[y * self.width + x])
+    }
+
+    // set ignores cells outside of the grid
+    pub fn set(&mut self, x: usize, y: usize, tile: TileId) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = tile;
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[TileId]> {
+        self.cells.chunks(self.width.max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_rows() {
+        let rows = vec![
+            vec![TileId(1), TileId(2), TileId(3)],
+            vec![TileId(4), TileId(5), TileId(6)],
+        ];
+        let grid = Grid::from_rows(rows).unwrap();
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid.get(2, 1), Some(TileId(6)));
+        assert_eq!(grid.get(3, 0), None);
+        assert_eq!(grid.get(0, 2), None);
+    }
+
+    #[test]
+    fn test_from_rows_rejects_ragged_rows() {
+        assert!(Grid::from_rows(vec![vec![TileId(1), TileId(2)], vec![TileId(3)]]).is_none());
+    }
+
+    #[test]
+    fn test_new_and_set() {
+        let mut grid = Grid::new(3, 2, TileId(1));
+        grid.set(1, 1, TileId::EMPTY);
+        grid.set(5, 5, TileId::EMPTY);
+        assert_eq!(grid.get(1, 1), Some(TileId::EMPTY));
+        assert_eq!(grid.get(0, 0), Some(TileId(1)));
+    }
+
+    #[test]
+    fn test_rows() {
+        let grid = Grid::from_rows(vec![vec![TileId::EMPTY; 64]; 48]).unwrap();
+        assert_eq!(grid.rows().count(), 48);
+        assert!(grid.rows().all(|row| row.len() == 64));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod grid;
+pub mod parser;
+pub mod validate;
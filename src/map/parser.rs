@@ -0,0 +1,125 @@
+use std::fmt;
+
+use crate::tile::{registry::TileRegistry, tile::TileId};
+
+use super::{grid::Grid, validate::check_rows};
+
+// Lines starting with this character are ignored by the parser
+const COMMENT_PREFIX: char = ';';
+
+#[derive(Debug, PartialEq)]
+pub struct MapParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl MapParseError {
+    pub fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+        MapParseError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for MapParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+// parse_map reads the plain text map format into a grid of tile ids.
+// Every non comment line is a row and every character a cell,
+// cells hold either the symbol of a registered tile or a digit
+// which is taken as the tile id itself.
+// Lines and columns in errors are 1-based.
+pub fn parse_map(source: &str, tiles: &TileRegistry) -> Result<Grid, MapParseError> {
+    let mut rows: Vec<Vec<TileId>> = Vec::new();
+    let mut row_lines = Vec::new();
+    let mut last_line = 0;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        last_line = line_number;
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with(COMMENT_PREFIX) {
+            continue;
+        }
+
+        let mut row = Vec::with_capacity(line.len());
+        for (column, c) in line.chars().enumerate() {
+            row.push(parse_cell(c, tiles).ok_or_else(|| {
+                MapParseError::new(line_number, column + 1, format!("unknown tile '{}'", c))
+            })?);
+        }
+
+        rows.push(row);
+        row_lines.push(line_number);
+    }
+
+    if rows.is_empty() {
+        return Err(MapParseError::new(last_line.max(1), 1, "map has no rows"));
+    }
+    if let Some(ragged) = check_rows(&rows).first() {
+        return Err(MapParseError::new(
+            row_lines[ragged.y as usize],
+            ragged.x as usize + 1,
+            ragged.kind.to_string(),
+        ));
+    }
+    Ok(Grid::from_rows(rows).expect("rows are checked to be the same length"))
+}
+
+fn parse_cell(c: char, tiles: &TileRegistry) -> Option<TileId> {
+    tiles
+        .by_symbol(c)
+        .or_else(|| c.to_digit(10).map(|d| TileId(d as u16)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_map_skips_comments_and_blank_lines() {
+        let grid = parse_map("; comment\n111\n\n1.2\n101\n", &TileRegistry::default()).unwrap();
+        let rows: Vec<Vec<u16>> = grid
+            .rows()
+            .map(|row| row.iter().map(|tile| tile.0).collect())
+            .collect();
+        assert_eq!(rows, vec![vec![1, 1, 1], vec![1, 0, 2], vec![1, 0, 1]]);
+    }
+
+    #[test]
+    fn test_parse_map_uses_registry_symbols() {
+        let tiles =
+            TileRegistry::from_toml("[[tile]]\nid = 42\nname = \"Hedge\"\nsymbol = \"h\"\n")
+                .unwrap();
+        let grid = parse_map("hhh\nh.h\nhhh", &tiles).unwrap();
+        assert_eq!(grid.get(0, 0), Some(TileId(42)));
+        assert_eq!(grid.get(1, 1), Some(TileId::EMPTY));
+    }
+
+    #[test]
+    fn test_parse_map_reports_unknown_tile_position() {
+        let err = parse_map("111\n1x1\n111", &TileRegistry::default()).unwrap_err();
+        assert_eq!(err, MapParseError::new(2, 2, "unknown tile 'x'"));
+    }
+
+    #[test]
+    fn test_parse_map_reports_ragged_row() {
+        let err = parse_map("111\n11\n111", &TileRegistry::default()).unwrap_err();
+        assert_eq!((err.line, err.column), (2, 3));
+    }
+
+    #[test]
+    fn test_parse_map_rejects_empty_source() {
+        assert!(parse_map("; nothing here\n", &TileRegistry::default()).is_err());
+    }
+}
@@ -0,0 +1,473 @@
+use std::{collections::VecDeque, fmt};
+
+use crate::{
+    level::{
+        level::{Entity, Spawn, EXIT_ENTITY},
+        portal::Portal,
+    },
+    tile::{registry::TileRegistry, tile::TileId},
+    window::window::TILE_SIZE,
+};
+
+use super::grid::Grid;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiagnosticKind {
+    // a row whose length differs from the first row
+    RaggedRow { expected: usize, found: usize },
+    // a border cell rays or the player could leave the map through
+    OpenBoundary,
+    // a cell holding an id missing from the tile registry
+    UnknownTile(TileId),
+    SpawnOutOfBounds,
+    SpawnInSolid,
+    // an exit the player cannot walk to from the spawn point
+    UnreachableExit,
+    // a door without walls on two opposite sides to slide into
+    DoorWithoutFrame,
+}
+
+// Diagnostic is a problem found in a map at the cell x, y.
+// Spawn cells outside of the map can have negative coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Diagnostic {
+    pub fn new(kind: DiagnosticKind, x: i64, y: i64) -> Self {
+        Diagnostic { kind, x, y }
+    }
+
+    // unknown tiles are drawn and collided with as plain walls,
+    // everything else makes the map unplayable
+    pub fn is_error(&self) -> bool {
+        !matches!(self.kind, DiagnosticKind::UnknownTile(_))
+    }
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagnosticKind::RaggedRow { expected, found } => {
+                write!(f, "expected {} columns, found {}", expected, found)
+            }
+            DiagnosticKind::OpenBoundary => write!(f, "outer boundary is open"),
+            DiagnosticKind::UnknownTile(id) => write!(f, "unknown tile id {}", id.0),
+            DiagnosticKind::SpawnOutOfBounds => write!(f, "spawn is outside of the map"),
+            DiagnosticKind::SpawnInSolid => write!(f, "spawn is inside a solid tile"),
+            DiagnosticKind::UnreachableExit => write!(f, "exit is unreachable from the spawn"),
+            DiagnosticKind::DoorWithoutFrame => write!(f, "door is not between two walls"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cell ({}, {}): {}", self.x, self.y, self.kind)
+    }
+}
+
+// check_rows reports every row that is not as long as the first one,
+// it runs before the rows are turned into a grid
+pub fn check_rows(rows: &[Vec<TileId>]) -> Vec<Diagnostic> {
+    let Some(expected) = rows.first().map(|row| row.len()) else {
+        return Vec::new();
+    };
+    rows.iter()
+        .enumerate()
+        .filter(|(_, row)| row.len() != expected)
+        .map(|(y, row)| {
+            Diagnostic::new(
+                DiagnosticKind::RaggedRow {
+                    expected,
+                    found: row.len(),
+                },
+                row.len().min(expected) as i64,
+                y as i64,
+            )
+        })
+        .collect()
+}
+
+// validate checks that a grid is safe to play: the border is closed,
+// every tile is known, doors sit between walls, the spawn is on a passable tile
+// and all exits, tiles or entities, can be walked to from the spawn,
+// through portals if need be
+pub fn validate(
+    grid: &Grid,
+    spawn: &Spawn,
+    portals: &[Portal],
+    entities: &[Entity],
+    tiles: &TileRegistry,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (y, row) in grid.rows().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            if tiles.get(*tile).is_none() {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::UnknownTile(*tile),
+                    x as i64,
+                    y as i64,
+                ));
+            }
+            if tiles.is_door(*tile) && !is_framed(grid, tiles, x, y) {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::DoorWithoutFrame,
+                    x as i64,
+                    y as i64,
+                ));
+            }
+            let on_border = x == 0 || y == 0 || x + 1 == grid.width() || y + 1 == grid.height();
+            let closed =
+                tiles.is_solid(*tile) && tiles.blocks_sight(*tile) && !tiles.is_see_through(*tile);
+            if on_border && !closed {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::OpenBoundary,
+                    x as i64,
+                    y as i64,
+                ));
+            }
+        }
+    }
+
+    let spawn_x = (spawn.x / TILE_SIZE as f64).floor() as i64;
+    let spawn_y = (spawn.y / TILE_SIZE as f64).floor() as i64;
+    let spawn_tile = usize::try_from(spawn_x)
+        .ok()
+        .zip(usize::try_from(spawn_y).ok())
+        .and_then(|(x, y)| grid.get(x, y));
+    match spawn_tile {
+        None => diagnostics.push(Diagnostic::new(
+            DiagnosticKind::SpawnOutOfBounds,
+            spawn_x,
+            spawn_y,
+        )),
+        Some(tile) if tiles.is_solid(tile) => diagnostics.push(Diagnostic::new(
+            DiagnosticKind::SpawnInSolid,
+            spawn_x,
+            spawn_y,
+        )),
+        Some(_) => {
+            let reached = flood_fill(grid, portals, tiles, spawn_x as usize, spawn_y as usize);
+            diagnostics.extend(unreachable_exits(grid, tiles, &reached));
+            diagnostics.extend(unreachable_exit_entities(grid, entities, &reached));
+        }
+    }
+
+    diagnostics
+}
+
+// is_framed tells whether the door at x, y has walls on both sides
+// along one of the axes
+fn is_framed(grid: &Grid, tiles: &TileRegistry, x: usize, y: usize) -> bool {
+    let is_wall = |x: usize, y: usize| {
+        grid.get(x, y)
+            .is_some_and(|tile| tiles.is_solid(tile) && !tiles.is_door(tile))
+    };
+    let [west, east, north, south] = neighbours(x, y).map(|(x, y)| is_wall(x, y));
+    (west && east) || (north && south)
+}
+
+// flood_fill marks every passable cell the player can walk to from x, y,
+// doors can be opened and are walked through, portals lead to the cells
+// around the joined portal
+fn flood_fill(
+    grid: &Grid,
+    portals: &[Portal],
+    tiles: &TileRegistry,
+    x: usize,
+    y: usize,
+) -> Vec<bool> {
+    let mut reached = vec![false; grid.width() * grid.height()];
+    let mut queue = VecDeque::from([(x, y)]);
+    reached[y * grid.width() + x] = true;
+
+    while let Some((x, y)) = queue.pop_front() {
+        for (nx, ny) in neighbours(x, y) {
+            let Some(tile) = grid.get(nx, ny) else {
+                continue;
+            };
+            let index = ny * grid.width() + nx;
+            let joined = portals
+                .iter()
+                .find_map(|portal| portal.other_side((nx, ny)));
+            if !reached[index] && (!tiles.is_solid(tile) || tiles.is_door(tile)) {
+                reached[index] = true;
+                queue.push_back((nx, ny));
+            } else if let Some(((px, py), _)) = joined.filter(|_| !reached[index]) {
+                reached[index] = true;
+                reached[py * grid.width() + px] = true;
+                queue.push_back((px, py));
+            }
+        }
+    }
+    reached
+}
+
+// An exit counts as reachable when the player can stand on it
+// or, for exits such as switches in walls, right next to it
+fn unreachable_exits(grid: &Grid, tiles: &TileRegistry, reached: &[bool]) -> Vec<Diagnostic> {
+    let is_reached = |x: usize, y: usize| x < grid.width() && reached[y * grid.width() + x];
+    let mut diagnostics = Vec::new();
+
+    for (y, row) in grid.rows().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            if !tiles.is_exit(*tile) {
+                continue;
+            }
+            let reachable = is_reached(x, y)
+                || neighbours(x, y)
+                    .into_iter()
+                    .any(|(nx, ny)| ny < grid.height() && is_reached(nx, ny));
+            if !reachable {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::UnreachableExit,
+                    x as i64,
+                    y as i64,
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+// Exit entities are used by walking into their cell, which must be reached
+fn unreachable_exit_entities(
+    grid: &Grid,
+    entities: &[Entity],
+    reached: &[bool],
+) -> Vec<Diagnostic> {
+    entities
+        .iter()
+        .filter(|entity| entity.kind == EXIT_ENTITY)
+        .map(|entity| {
+            (
+                (entity.x / TILE_SIZE as f64).floor() as i64,
+                (entity.y / TILE_SIZE as f64).floor() as i64,
+            )
+        })
+        .filter(|&(x, y)| {
+            let inside =
+                (0..grid.width() as i64).contains(&x) && (0..grid.height() as i64).contains(&y);
+            !(inside && reached[y as usize * grid.width() + x as usize])
+        })
+        .map(|(x, y)| Diagnostic::new(DiagnosticKind::UnreachableExit, x, y))
+        .collect()
+}
+
+// neighbours wraps around below zero so that the cells are dropped by bounds checks
+fn neighbours(x: usize, y: usize) -> [(usize, usize); 4] {
+    [
+        (x.wrapping_sub(1), y),
+        (x + 1, y),
+        (x, y.wrapping_sub(1)),
+        (x, y + 1),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{level::level::Level, map::parser::parse_map};
+
+    const TILES: &str = r##"
+        [[tile]]
+        id = 1
+        name = "Wall"
+        symbol = "#"
+
+        [[tile]]
+        id = 2
+        name = "Exit"
+        symbol = "E"
+        solid = false
+        blocks_sight = false
+        exit = true
+
+        [[tile]]
+        id = 3
+        name = "Switch"
+        symbol = "S"
+        exit = true
+
+        [[tile]]
+        id = 4
+        name = "Door"
+        symbol = "D"
+        door = true
+
+        [[tile]]
+        id = 5
+        name = "Portal"
+        symbol = "O"
+        portal = true
+    "##;
+
+    fn spawn_at(x: f64, y: f64) -> Spawn {
+        let tile = TILE_SIZE as f64;
+        Spawn {
+            x: x * tile,
+            y: y * tile,
+            angle: 0.0,
+        }
+    }
+
+    fn check(map: &str, spawn: Spawn) -> Vec<Diagnostic> {
+        let tiles = TileRegistry::from_toml(TILES).unwrap();
+        let grid = parse_map(map, &tiles).unwrap();
+        validate(&grid, &spawn, &[], &[], &tiles)
+    }
+
+    #[test]
+    fn test_valid_map() {
+        let map = "#####\n#..E#\n#...S\n#####\n";
+        assert!(check(map, spawn_at(1.5, 1.5)).is_empty());
+    }
+
+    #[test]
+    fn test_default_level_is_valid() {
+        let level = Level::default();
+        assert!(validate(
+            &level.grid,
+            &level.spawn,
+            &level.portals,
+            &level.entities,
+            &TileRegistry::default()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_open_boundary() {
+        let diagnostics = check("##.#\n#..#\n####\n", spawn_at(1.5, 1.5));
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic::new(DiagnosticKind::OpenBoundary, 2, 0)]
+        );
+        assert!(diagnostics[0].is_error());
+    }
+
+    #[test]
+    fn test_unknown_tiles_are_warnings() {
+        let diagnostics = check("#9#\n#.#\n###\n", spawn_at(1.5, 1.5));
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic::new(
+                DiagnosticKind::UnknownTile(TileId(9)),
+                1,
+                0
+            )]
+        );
+        assert!(!diagnostics[0].is_error());
+    }
+
+    #[test]
+    fn test_spawn_checks() {
+        let map = "###\n#.#\n###\n";
+        assert_eq!(
+            check(map, spawn_at(0.5, 1.5)),
+            vec![Diagnostic::new(DiagnosticKind::SpawnInSolid, 0, 1)]
+        );
+        assert_eq!(
+            check(map, spawn_at(-1.0, 4.0)),
+            vec![Diagnostic::new(DiagnosticKind::SpawnOutOfBounds, -1, 4)]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_exits() {
+        let map = "######\n#.#.E#\n#.#.S#\n######\n";
+        assert_eq!(
+            check(map, spawn_at(1.5, 1.5)),
+            vec![
+                Diagnostic::new(DiagnosticKind::UnreachableExit, 4, 1),
+                Diagnostic::new(DiagnosticKind::UnreachableExit, 4, 2),
+            ]
+        );
+        assert!(check(map, spawn_at(3.5, 2.5)).is_empty());
+    }
+
+    #[test]
+    fn test_unreachable_exit_entities() {
+        let tiles = TileRegistry::from_toml(TILES).unwrap();
+        let grid = parse_map("######\n#.#..#\n######\n", &tiles).unwrap();
+        let spawn = spawn_at(1.5, 1.5);
+        let exit = |x: f64, y: f64| {
+            let tile = TILE_SIZE as f64;
+            Entity {
+                kind: String::from(EXIT_ENTITY),
+                name: String::new(),
+                x: x * tile,
+                y: y * tile,
+                angle: 0.0,
+                properties: Default::default(),
+            }
+        };
+        // the exit is walled off from the spawn, or outside of the map
+        assert_eq!(
+            validate(
+                &grid,
+                &spawn,
+                &[],
+                &[exit(3.5, 1.5), exit(9.5, 1.5)],
+                &tiles
+            ),
+            vec![
+                Diagnostic::new(DiagnosticKind::UnreachableExit, 3, 1),
+                Diagnostic::new(DiagnosticKind::UnreachableExit, 9, 1),
+            ]
+        );
+        assert!(validate(&grid, &spawn_at(4.5, 1.5), &[], &[exit(3.5, 1.5)], &tiles).is_empty());
+    }
+
+    #[test]
+    fn test_doors() {
+        // exits behind doors can be reached
+        let map = "######\n#.D.E#\n######\n";
+        assert!(check(map, spawn_at(1.5, 1.5)).is_empty());
+        let map = "######\n#.#..#\n#.D.E#\n#....#\n######\n";
+        assert_eq!(
+            check(map, spawn_at(1.5, 1.5)),
+            vec![Diagnostic::new(DiagnosticKind::DoorWithoutFrame, 2, 2)]
+        );
+    }
+
+    #[test]
+    fn test_portals() {
+        // the exit is only reached through the portals
+        let tiles = TileRegistry::from_toml(TILES).unwrap();
+        let grid = parse_map("#######\n#.O#OE#\n#######\n", &tiles).unwrap();
+        let spawn = spawn_at(1.5, 1.5);
+        assert_eq!(
+            validate(&grid, &spawn, &[], &[], &tiles),
+            vec![Diagnostic::new(DiagnosticKind::UnreachableExit, 5, 1)]
+        );
+        let portal = Portal {
+            a: (2, 1),
+            b: (4, 1),
+            turns: 0,
+        };
+        assert!(validate(&grid, &spawn, &[portal], &[], &tiles).is_empty());
+    }
+
+    #[test]
+    fn test_check_rows() {
+        let rows = vec![vec![TileId(1); 4], vec![TileId(1); 4], vec![TileId(1); 2]];
+        assert_eq!(
+            check_rows(&rows),
+            vec![Diagnostic::new(
+                DiagnosticKind::RaggedRow {
+                    expected: 4,
+                    found: 2
+                },
+                2,
+                2
+            )]
+        );
+        assert!(check_rows(&[]).is_empty());
+    }
+}
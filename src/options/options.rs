@@ -0,0 +1,199 @@
+use crate::{
+    generator::generator::{Algorithm, DEFAULT_SIZE},
+    window::window::Resolution,
+};
+
+pub const DEFAULT_TEXTURES_DIR: &str = "assets/textures";
+
+// Options holds the command line configuration of the engine
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub map_path: Option<String>,
+    pub tiles_path: Option<String>,
+    pub actors_path: Option<String>,
+    pub campaign_path: Option<String>,
+    // textures_dir holds the walls, sprites and ui texture directories
+    pub textures_dir: String,
+    pub map_number: usize,
+    pub resolution: Resolution,
+    pub scale: u32,
+    // generate starts on a generated map instead of loading one
+    pub generate: Option<Algorithm>,
+    pub seed: Option<u64>,
+    // size of generated maps in tiles
+    pub size: (usize, usize),
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            map_path: None,
+            tiles_path: None,
+            actors_path: None,
+            campaign_path: None,
+            textures_dir: String::from(DEFAULT_TEXTURES_DIR),
+            map_number: 0,
+            resolution: Resolution::default(),
+            scale: 1,
+            generate: None,
+            seed: None,
+            size: DEFAULT_SIZE,
+        }
+    }
+}
+
+impl Options {
+    // parse reads the arguments following the program name:
+    // [--resolution WIDTHxHEIGHT] [--scale N] [--tiles TILES] [--map-number N]
+    // [--generate ALGORITHM] [--seed N] [--size WIDTHxHEIGHT] [--campaign CAMPAIGN]
+    // [--textures DIR] [--actors ACTORS] [MAP]
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--resolution" | "-r" => {
+                    options.resolution = Self::value(&arg, args.next())?.parse()?;
+                }
+                "--scale" | "-s" => {
+                    let value = Self::value(&arg, args.next())?;
+                    options.scale = match value.parse() {
+                        Ok(scale) if scale > 0 => scale,
+                        _ => return Err(format!("Invalid scale {}", value)),
+                    };
+                }
+                "--tiles" | "-t" => {
+                    options.tiles_path = Some(Self::value(&arg, args.next())?);
+                }
+                "--actors" => {
+                    options.actors_path = Some(Self::value(&arg, args.next())?);
+                }
+                "--textures" => {
+                    options.textures_dir = Self::value(&arg, args.next())?;
+                }
+                "--campaign" | "-c" => {
+                    options.campaign_path = Some(Self::value(&arg, args.next())?);
+                }
+                "--map-number" | "-n" => {
+                    let value = Self::value(&arg, args.next())?;
+                    options.map_number = value
+                        .parse()
+                        .map_err(|_| format!("Invalid map number {}", value))?;
+                }
+                "--generate" | "-g" => {
+                    options.generate = Some(Self::value(&arg, args.next())?.parse()?);
+                }
+                "--seed" => {
+                    let value = Self::value(&arg, args.next())?;
+                    options.seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("Invalid seed {}", value))?,
+                    );
+                }
+                "--size" => {
+                    options.size = Self::size(&Self::value(&arg, args.next())?)?;
+                }
+                _ if arg.starts_with('-') => return Err(format!("Unknown option {}", arg)),
+                _ if options.map_path.is_none() => options.map_path = Some(arg),
+                _ => return Err(format!("Unexpected argument {}", arg)),
+            }
+        }
+        let sources = [
+            options.generate.is_some(),
+            options.map_path.is_some(),
+            options.campaign_path.is_some(),
+        ];
+        if sources.iter().filter(|source| **source).count() > 1 {
+            return Err(String::from(
+                "Only one of a map, a campaign or a generated level can be played",
+            ));
+        }
+        Ok(options)
+    }
+
+    // window_size is the output resolution multiplied by the scale
+    pub fn window_size(&self) -> (u32, u32) {
+        (
+            self.resolution.width * self.scale,
+            self.resolution.height * self.scale,
+        )
+    }
+
+    // size reads a map size written as WIDTHxHEIGHT in tiles
+    fn size(value: &str) -> Result<(usize, usize), String> {
+        value
+            .split_once(['x', 'X'])
+            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+            .ok_or_else(|| format!("Invalid size {}, expected WIDTHxHEIGHT", value))
+    }
+
+    fn value(option: &str, value: Option<String>) -> Result<String, String> {
+        value.ok_or_else(|| format!("Missing value for {}", option))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_defaults() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+    }
+
+    #[test]
+    fn test_parse_resolution_scale_and_map() {
+        let options = parse(&["--resolution", "320x200", "maps/default.map", "-s", "3"]).unwrap();
+        assert_eq!(options.tiles_path, None);
+        assert_eq!(options.actors_path, None);
+        assert_eq!(options.map_number, 0);
+        assert_eq!(options.resolution, Resolution::new(320, 200));
+        assert_eq!(options.map_path.as_deref(), Some("maps/default.map"));
+        assert_eq!(options.window_size(), (960, 600));
+    }
+
+    #[test]
+    fn test_parse_generator() {
+        let options = parse(&["--generate", "caves", "--seed", "12", "--size", "40x30"]).unwrap();
+        assert_eq!(options.generate, Some(Algorithm::Caves));
+        assert_eq!(options.seed, Some(12));
+        assert_eq!(options.size, (40, 30));
+        assert_eq!(options.map_path, None);
+    }
+
+    #[test]
+    fn test_parse_campaign() {
+        let options = parse(&["-c", "maps/campaign.toml"]).unwrap();
+        assert_eq!(options.campaign_path.as_deref(), Some("maps/campaign.toml"));
+        assert_eq!(options.textures_dir, DEFAULT_TEXTURES_DIR);
+    }
+
+    #[test]
+    fn test_parse_definitions() {
+        let options = parse(&["-t", "my_tiles.toml", "--actors", "my_actors.toml"]).unwrap();
+        assert_eq!(options.tiles_path.as_deref(), Some("my_tiles.toml"));
+        assert_eq!(options.actors_path.as_deref(), Some("my_actors.toml"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&["--resolution"]).is_err());
+        assert!(parse(&["--scale", "0"]).is_err());
+        assert!(parse(&["--fullscreen"]).is_err());
+        assert!(parse(&["--tiles"]).is_err());
+        assert!(parse(&["--actors"]).is_err());
+        assert!(parse(&["--map-number", "-1"]).is_err());
+        assert!(parse(&["a.map", "b.map"]).is_err());
+        assert!(parse(&["--generate", "dungeon"]).is_err());
+        assert!(parse(&["--generate", "maze", "a.map"]).is_err());
+        assert!(parse(&["--seed", "-3"]).is_err());
+        assert!(parse(&["--campaign", "maps/campaign.toml", "a.map"]).is_err());
+        assert!(parse(&["--size", "40"]).is_err());
+    }
+}
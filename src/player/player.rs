@@ -2,7 +2,8 @@ use std::f64::consts::PI;
 
 use crate::{
     game::game::Game,
-    window::window::{MINIMAP_SCALING, WINDOW_HEIGHT, WINDOW_WIDTH},
+    level::level::Spawn,
+    window::window::{MINIMAP_SCALING, TILE_SIZE},
 };
 
 #[derive(Clone, Copy)]
@@ -12,6 +13,13 @@ pub enum TurnDirection {
     Right = 1,
 }
 
+#[derive(Clone, Copy)]
+pub enum LookDirection {
+    Neutral = 0,
+    Up = -1,
+    Down = 1,
+}
+
 #[derive(Clone, Copy)]
 pub enum WalkDirection {
     Neutral = 0,
@@ -19,6 +27,47 @@ pub enum WalkDirection {
     Backward = -1,
 }
 
+// How far the player can look up or down, in radians
+pub const MAX_PITCH: f64 = 0.5;
+// Eye heights above the floor standing and crouching
+pub const STAND_HEIGHT: f64 = TILE_SIZE as f64 / 2.0;
+pub const CROUCH_HEIGHT: f64 = TILE_SIZE as f64 / 4.0;
+// How fast the eye moves towards the standing or crouching height
+const CROUCH_SPEED: f64 = 96.0;
+// Upward speed at the start of a jump and the gravity pulling it back
+const JUMP_SPEED: f64 = 160.0;
+const GRAVITY: f64 = 640.0;
+// How high a step the player walks up, and how close the eye comes to the ceiling
+const MAX_STEP: f64 = TILE_SIZE as f64 / 8.0;
+const HEADROOM: f64 = TILE_SIZE as f64 / 16.0;
+
+// Stats are carried by the player from one level of a campaign to the next
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub level_time: f64,
+    pub total_time: f64,
+    pub levels_completed: u32,
+    // secrets found and secrets there were in the completed levels
+    pub secrets_found: u32,
+    pub secrets: u32,
+}
+
+impl Stats {
+    pub fn tick(&mut self, delta: f64) {
+        self.level_time += delta;
+        self.total_time += delta;
+    }
+
+    // finish_level counts the level as completed along with its secrets and
+    // returns the time it took, the next level starts from zero
+    pub fn finish_level(&mut self, secrets_found: u32, secrets: u32) -> f64 {
+        self.levels_completed += 1;
+        self.secrets_found += secrets_found;
+        self.secrets += secrets;
+        std::mem::take(&mut self.level_time)
+    }
+}
+
 pub struct Player {
     pub x: f64,
     pub y: f64,
@@ -26,36 +75,106 @@ pub struct Player {
     pub height: f64,
     pub turn_direction: TurnDirection,
     pub walk_direction: WalkDirection,
+    pub look_direction: LookDirection,
     pub rotation_angle: f64,
+    // pitch is how far the player looks up or down, moving the horizon
+    pub pitch: f64,
+    // floor_height is how high the floor the player stands on is raised,
+    // camera_height the eye height standing or crouching on it,
+    // jump_height what a jump adds to it, rising at vertical_speed
+    pub floor_height: f64,
+    pub camera_height: f64,
+    pub crouching: bool,
+    pub jump_height: f64,
+    pub vertical_speed: f64,
     pub walk_speed: f64,
     pub turn_speed: f64,
+    pub look_speed: f64,
     pub minimap_scale: f64,
+    pub stats: Stats,
 }
 
-// Default returns an owned player
-// in the x and y postion
-// with 1 pixel witdh and height
-// with neutral turn and walk diretion
-// 100 pixel walkspeed and 45 radians turning
-// the default minimapScaling is 0.3
-impl Default for Player {
-    fn default() -> Self {
+impl Player {
+    // New returns an owned player
+    // in the x and y world postion facing rotation_angle
+    // with 1 pixel witdh and height
+    // with neutral turn and walk diretion
+    // 100 pixel walkspeed and 45 radians turning
+    // the default minimapScaling is 0.3
+    pub fn new(x: f64, y: f64, rotation_angle: f64) -> Self {
         Player {
-            x: WINDOW_WIDTH as f64 / 2.0,
-            y: WINDOW_HEIGHT as f64 / 2.0,
+            x,
+            y,
             width: 1.0,
             height: 1.0,
             turn_direction: TurnDirection::Neutral,
             walk_direction: WalkDirection::Neutral,
-            rotation_angle: PI / 2.0,
+            look_direction: LookDirection::Neutral,
+            rotation_angle,
+            pitch: 0.0,
+            floor_height: 0.0,
+            camera_height: STAND_HEIGHT,
+            crouching: false,
+            jump_height: 0.0,
+            vertical_speed: 0.0,
             walk_speed: 100.0,
             turn_speed: 45.0 * (PI / 180.0),
+            look_speed: 45.0 * (PI / 180.0),
             minimap_scale: MINIMAP_SCALING,
+            stats: Stats::default(),
+        }
+    }
+
+    // respawn moves the player to the spawn point of a new level
+    // keeping its stats, speeds and the keys being held
+    pub fn respawn(&mut self, spawn: &Spawn) {
+        self.x = spawn.x;
+        self.y = spawn.y;
+        self.rotation_angle = spawn.angle;
+    }
+
+    // eye_height is how high above the ground the player looks from,
+    // kept under the ceiling
+    pub fn eye_height(&self) -> f64 {
+        (self.floor_height + self.camera_height + self.jump_height).min(TILE_SIZE as f64 - HEADROOM)
+    }
+
+    // jump starts a jump unless the player is in the air already
+    pub fn jump(&mut self) {
+        if self.jump_height <= 0.0 {
+            self.vertical_speed = JUMP_SPEED;
+        }
+    }
+
+    // center_view looks straight ahead again
+    pub fn center_view(&mut self) {
+        self.pitch = 0.0;
+    }
+
+    // update_camera moves the view by delta seconds: the pitch follows the look
+    // direction, the eye eases towards the standing or crouching height
+    // and jumps rise and fall back to the floor
+    pub fn update_camera(&mut self, delta: f64) {
+        let look = self.look_direction as i32 as f64;
+        self.pitch = (self.pitch + look * self.look_speed * delta).clamp(-MAX_PITCH, MAX_PITCH);
+
+        let target = match self.crouching {
+            true => CROUCH_HEIGHT,
+            false => STAND_HEIGHT,
+        };
+        let step = CROUCH_SPEED * delta;
+        self.camera_height += (target - self.camera_height).clamp(-step, step);
+
+        if self.jump_height > 0.0 || self.vertical_speed > 0.0 {
+            self.jump_height += self.vertical_speed * delta;
+            self.vertical_speed -= GRAVITY * delta;
+            if self.jump_height <= 0.0 {
+                self.jump_height = 0.0;
+                self.vertical_speed = 0.0;
+            }
         }
     }
-}
 
-impl Player {
     fn set_x(&mut self, x: f64) {
         self.x = x;
     }
@@ -74,6 +193,16 @@ impl Player {
         Ok(())
     }
 
+    pub fn set_look_direction(&mut self, direction: &str) -> Result<(), String> {
+        match direction.to_lowercase().as_str() {
+            "neutral" => self.look_direction = LookDirection::Neutral,
+            "up" => self.look_direction = LookDirection::Up,
+            "down" => self.look_direction = LookDirection::Down,
+            _ => return Err(format!("Unknown look direction {}", direction)),
+        }
+        Ok(())
+    }
+
     pub fn set_turn_direction(&mut self, direction: &str) -> Result<(), String> {
         match direction.to_lowercase().as_str() {
             "neutral" => self.turn_direction = TurnDirection::Neutral,
@@ -87,8 +216,14 @@ impl Player {
     // Move changes the value of the x and y fiels of a player object
     // to new values considering the turn and walk speeds
     // accounting fot the delta time
-    // and checking for collision into the new position
+    // and checking for collision into the new position.
+    // The player steps up and down raised floors, unless too high a step.
     pub fn move_player(&mut self, delta: f64, game: &Game) {
+        self.walk(delta, game);
+        self.floor_height = game.floor_height(self.x, self.y);
+    }
+
+    fn walk(&mut self, delta: f64, game: &Game) {
         self.rotation_angle += match self.turn_direction {
             TurnDirection::Neutral => 0.0,
             TurnDirection::Left => -self.turn_speed * delta,
@@ -104,9 +239,170 @@ impl Player {
         let new_x = self.x + self.rotation_angle.cos() * distance;
         let new_y = self.y + self.rotation_angle.sin() * distance;
 
-        if !game.is_coordinate_solid(new_x, new_y) {
+        // stepping into a portal takes the player out of the joined one,
+        // turned the same way as the portal turns the view
+        let heading = match distance < 0.0 {
+            true => self.rotation_angle + PI,
+            false => self.rotation_angle,
+        };
+        if let Some((x, y, angle)) = game.pass_through(new_x, new_y, heading) {
+            if !game.is_coordinate_solid(x, y) {
+                self.set_x(x);
+                self.set_y(y);
+                self.rotation_angle += angle - heading;
+            }
+            return;
+        }
+
+        if !game.is_coordinate_solid(new_x, new_y)
+            && !game.hits_segment(self.x, self.y, new_x, new_y)
+            && game.floor_height(new_x, new_y) - game.floor_height(self.x, self.y) <= MAX_STEP
+        {
             self.set_x(new_x);
             self.set_y(new_y);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{level::level::Level, sprite::actor::ActorRegistry, tile::registry::TileRegistry};
+
+    #[test]
+    fn test_respawn_keeps_stats() {
+        let mut player = Player::new(10.0, 10.0, 0.0);
+        player.stats.tick(2.5);
+        player.set_walk_direction("forward").unwrap();
+        assert_eq!(player.stats.finish_level(1, 3), 2.5);
+
+        player.respawn(&Spawn {
+            x: 96.0,
+            y: 32.0,
+            angle: PI,
+        });
+        player.stats.tick(1.0);
+        assert_eq!(
+            (player.x, player.y, player.rotation_angle),
+            (96.0, 32.0, PI)
+        );
+        assert!(matches!(player.walk_direction, WalkDirection::Forward));
+        assert_eq!(
+            player.stats,
+            Stats {
+                level_time: 1.0,
+                total_time: 3.5,
+                levels_completed: 1,
+                secrets_found: 1,
+                secrets: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_look_up_and_down() {
+        let mut player = Player::new(10.0, 10.0, 0.0);
+        player.set_look_direction("up").unwrap();
+        player.update_camera(0.25);
+        assert!(player.pitch < 0.0);
+        player.update_camera(10.0);
+        assert_eq!(player.pitch, -MAX_PITCH);
+        player.set_look_direction("down").unwrap();
+        player.update_camera(100.0);
+        assert_eq!(player.pitch, MAX_PITCH);
+        player.center_view();
+        assert_eq!(player.pitch, 0.0);
+        assert!(player.set_look_direction("sideways").is_err());
+    }
+
+    #[test]
+    fn test_crouch_and_jump() {
+        let mut player = Player::new(10.0, 10.0, 0.0);
+        assert_eq!(player.eye_height(), STAND_HEIGHT);
+        player.crouching = true;
+        player.update_camera(0.125);
+        assert!(player.eye_height() < STAND_HEIGHT && player.eye_height() > CROUCH_HEIGHT);
+        player.update_camera(1.0);
+        assert_eq!(player.eye_height(), CROUCH_HEIGHT);
+        player.crouching = false;
+        player.update_camera(1.0);
+
+        // a jump rises, cannot be started again in the air and lands
+        player.jump();
+        player.update_camera(0.125);
+        let height = player.jump_height;
+        assert!(height > 0.0);
+        player.jump();
+        player.update_camera(0.125);
+        assert!(player.jump_height > height);
+        for _ in 0..8 {
+            player.update_camera(0.125);
+        }
+        assert_eq!((player.jump_height, player.vertical_speed), (0.0, 0.0));
+        assert_eq!(player.eye_height(), STAND_HEIGHT);
+    }
+
+    #[test]
+    fn test_walk_through_portal() {
+        let source =
+            "name = \"Portals\"\nmap = \"\"\"\n1111111\n1O....1\n11111O1\n1111111\n\"\"\"\n\
+            [[portal]]\na = [1, 1]\nb = [5, 2]\nturn = 90\n";
+        let tiles = TileRegistry::default();
+        let level = Level::from_toml(source, &tiles).unwrap();
+        let game = Game::from_level(level, tiles, ActorRegistry::default());
+        let tile = TILE_SIZE as f64;
+
+        // walking west into the portal comes out of the south side of the
+        // other one heading north, back into the corridor
+        let mut player = Player::new(2.1 * tile, 1.5 * tile, PI);
+        player.set_walk_direction("forward").unwrap();
+        player.move_player(0.125, &game);
+        assert_eq!((player.x / tile).floor(), 5.0);
+        assert_eq!((player.y / tile).floor(), 1.0);
+        assert!((player.rotation_angle - 1.5 * PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_walk_up_and_down_stairs() {
+        let tiles = TileRegistry::default();
+        let map = "1111111\n1.abcb1\n1111111\n";
+        let level = Level::from_map_source(String::from("stairs"), map, &tiles).unwrap();
+        let game = Game::from_level(level, tiles, ActorRegistry::default());
+        let tile = TILE_SIZE as f64;
+
+        // walking east climbs a step per tile, the eye rising with the floor
+        let mut player = Player::new(1.5 * tile, 1.5 * tile, 0.0);
+        player.set_walk_direction("forward").unwrap();
+        let mut heights = Vec::new();
+        for _ in 0..4 {
+            player.move_player(tile / player.walk_speed, &game);
+            heights.push(player.eye_height() - STAND_HEIGHT);
+        }
+        assert_eq!(
+            heights,
+            vec![tile / 8.0, tile / 4.0, 3.0 * tile / 8.0, tile / 4.0]
+        );
+
+        // walking back down is as easy, a step too high is not climbed
+        player.rotation_angle = PI;
+        for _ in 0..4 {
+            player.move_player(tile / player.walk_speed, &game);
+        }
+        assert_eq!(player.eye_height(), STAND_HEIGHT);
+        player.x = 1.5 * tile;
+        let game = Game::from_level(
+            Level::from_map_source(String::from("ledge"), "11111\n1.c.1\n11111\n", &game.tiles)
+                .unwrap(),
+            game.tiles.clone(),
+            ActorRegistry::default(),
+        );
+        player.rotation_angle = 0.0;
+        player.move_player(tile / player.walk_speed, &game);
+        assert_eq!((player.x, player.eye_height()), (1.5 * tile, STAND_HEIGHT));
+
+        // the eye stays under the ceiling jumping off the highest step
+        player.floor_height = 3.0 * tile / 8.0;
+        player.jump_height = tile / 2.0;
+        assert!(player.eye_height() < tile);
+    }
+}
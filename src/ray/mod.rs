@@ -1 +1,2 @@
 pub mod ray;
+pub mod view;
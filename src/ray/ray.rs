@@ -1,4 +1,7 @@
-use crate::utils::geometry::{is_angle_facing_down, is_angle_facing_right, normalize_angle};
+use crate::{
+    tile::tile::{Face, TileId},
+    utils::geometry::{is_angle_facing_down, is_angle_facing_right, normalize_angle},
+};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Ray {
@@ -11,7 +14,25 @@ pub struct Ray {
     pub is_facing_down: bool,
     pub is_facing_left: bool,
     pub is_facing_right: bool,
-    pub content: i32,
+    pub content: TileId,
+    // texture_offset is where the ray hit along the face, from 0 to a tile
+    pub texture_offset: f64,
+    // base is the floor height in front of the wall, where it is seen standing
+    pub base: f64,
+    // view is the index of the view the wall is seen through
+    pub view: usize,
+    // next is where the ray goes on past a portal or a mirror
+    pub next: Option<Leg>,
+}
+
+// Leg is the part of a ray past a portal or a mirror: it leaves x, y along
+// angle and what it meets is seen through the view of index view
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Leg {
+    pub x: f64,
+    pub y: f64,
+    pub angle: f64,
+    pub view: usize,
 }
 
 impl Ray {
@@ -32,7 +53,19 @@ impl Ray {
             is_facing_down,
             is_facing_left,
             is_facing_right,
-            content: i32::default(),
+            content: TileId::EMPTY,
+            texture_offset: 0.0,
+            base: 0.0,
+            view: 0,
+            next: None,
         }
     }
+
+    pub fn face(&self) -> Face {
+        Face::from_hit(
+            self.is_vertical_collision,
+            self.is_facing_right,
+            self.is_facing_down,
+        )
+    }
 }
@@ -0,0 +1,131 @@
+// View places what a ray sees past portals and mirrors along the straight
+// ray of its screen column. A point x, y of the world seen through the view
+// appears at matrix * (x, y) + offset, as if there were no portal or mirror.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    pub matrix: [f64; 4],
+    pub offset: (f64, f64),
+    // mirrored views show the world the other way around
+    pub mirrored: bool,
+}
+
+// How far apart two views may place a point and still be the same view
+const SAME_VIEW: f64 = 1e-3;
+
+impl View {
+    pub const IDENTITY: View = View {
+        matrix: [1.0, 0.0, 0.0, 1.0],
+        offset: (0.0, 0.0),
+        mirrored: false,
+    };
+
+    // new returns the view of a ray leaving x, y along angle that appears
+    // to leave apparent along apparent_angle
+    pub fn new(
+        x: f64,
+        y: f64,
+        angle: f64,
+        apparent: (f64, f64),
+        apparent_angle: f64,
+        mirrored: bool,
+    ) -> View {
+        let matrix = match mirrored {
+            // turning by the difference of the headings
+            false => {
+                let (sin, cos) = (apparent_angle - angle).sin_cos();
+                [cos, -sin, sin, cos]
+            }
+            // reflecting across the line halfway between the headings
+            true => {
+                let (sin, cos) = (apparent_angle + angle).sin_cos();
+                [cos, sin, sin, -cos]
+            }
+        };
+        // the offset brings the start of the ray onto where it appears
+        let mut view = View {
+            matrix,
+            offset: (0.0, 0.0),
+            mirrored,
+        };
+        let (x, y) = view.apply(x, y);
+        view.offset = (apparent.0 - x, apparent.1 - y);
+        view
+    }
+
+    // apply returns where the point x, y appears
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let [a, b, c, d] = self.matrix;
+        (a * x + b * y + self.offset.0, c * x + d * y + self.offset.1)
+    }
+
+    // unapply returns the point of the world appearing at x, y,
+    // the matrix only turns and reflects so its transpose undoes it
+    pub fn unapply(&self, x: f64, y: f64) -> (f64, f64) {
+        let [a, b, c, d] = self.matrix;
+        let (x, y) = (x - self.offset.0, y - self.offset.1);
+        (a * x + c * y, b * x + d * y)
+    }
+
+    // unapply_angle returns the heading in the world of a heading seen as angle
+    pub fn unapply_angle(&self, angle: f64) -> f64 {
+        let [a, b, c, d] = self.matrix;
+        let (sin, cos) = angle.sin_cos();
+        (b * cos + d * sin).atan2(a * cos + c * sin)
+    }
+
+    // same tells whether two views place the world alike, up to rounding
+    pub fn same(&self, other: &View) -> bool {
+        self.mirrored == other.mirrored
+            && self
+                .matrix
+                .iter()
+                .zip(other.matrix)
+                .all(|(a, b)| (a - b).abs() < SAME_VIEW / 1000.0)
+            && (self.offset.0 - other.offset.0).abs() < SAME_VIEW
+            && (self.offset.1 - other.offset.1).abs() < SAME_VIEW
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    fn assert_near((x, y): (f64, f64), (expected_x, expected_y): (f64, f64)) {
+        assert!(
+            (x - expected_x).abs() < 1e-9 && (y - expected_y).abs() < 1e-9,
+            "({}, {}) is not ({}, {})",
+            x,
+            y,
+            expected_x,
+            expected_y
+        );
+    }
+
+    #[test]
+    fn test_mirror_view() {
+        // a ray heading east from 100, 50 bounces back off a mirror at x = 192
+        let view = View::new(192.0, 50.0, PI, (192.0, 50.0), 0.0, true);
+        assert_near(view.apply(100.0, 50.0), (284.0, 50.0));
+        assert_near(view.apply(150.0, 80.0), (234.0, 80.0));
+        assert_near(view.unapply(284.0, 50.0), (100.0, 50.0));
+        // looking east into the mirror is looking west in the world
+        assert!((view.unapply_angle(0.0).abs() - PI).abs() < 1e-9);
+        // rays bouncing off the same mirror elsewhere see the same view
+        let other = View::new(192.0, 20.0, 1.25 * PI, (192.0, 20.0), -0.25 * PI, true);
+        assert!(view.same(&other));
+        assert!(!view.same(&View::IDENTITY));
+    }
+
+    #[test]
+    fn test_turned_view() {
+        // a portal sends a ray heading east at 64, 32 on south from 300, 200
+        let view = View::new(300.0, 200.0, PI / 2.0, (64.0, 32.0), 0.0, false);
+        assert_near(view.apply(300.0, 264.0), (128.0, 32.0));
+        assert_near(view.apply(250.0, 200.0), (64.0, 82.0));
+        assert_near(view.unapply(128.0, 32.0), (300.0, 264.0));
+        assert!((view.unapply_angle(0.0) - PI / 2.0).abs() < 1e-9);
+        assert!(View::IDENTITY.same(&View::new(5.0, 6.0, 1.0, (5.0, 6.0), 1.0, false)));
+    }
+}
@@ -0,0 +1,196 @@
+use std::{collections::BTreeMap, f64::consts::TAU, fs};
+
+use serde::Deserialize;
+
+use super::animation::Sequence;
+
+const BUILTIN_ACTORS: &str = include_str!("../../assets/actors.toml");
+
+#[derive(Deserialize)]
+struct ActorFile {
+    #[serde(default)]
+    actor: Vec<RawActorDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawActorDef {
+    kind: String,
+    sprite: String,
+    #[serde(default = "default_rotations")]
+    rotations: u32,
+    #[serde(default)]
+    sequence: Vec<RawSequence>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSequence {
+    name: String,
+    frames: Vec<u32>,
+    duration: f64,
+    #[serde(default = "default_true", rename = "loop")]
+    looping: bool,
+}
+
+fn default_rotations() -> u32 {
+    8
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl RawActorDef {
+    fn into_def(self) -> Result<ActorDef, String> {
+        if !matches!(self.rotations, 1 | 8) {
+            return Err(format!("expected 1 or 8 rotations, got {}", self.rotations));
+        }
+        if self.sequence.is_empty() {
+            return Err(String::from("expected at least one sequence"));
+        }
+        let sequences = self
+            .sequence
+            .into_iter()
+            .map(|raw| {
+                if raw.frames.is_empty() {
+                    return Err(format!("sequence {} has no frames", raw.name));
+                }
+                if raw.duration <= 0.0 {
+                    return Err(format!("sequence {} needs a positive duration", raw.name));
+                }
+                Ok(Sequence {
+                    name: raw.name,
+                    frames: raw.frames,
+                    duration: raw.duration,
+                    looping: raw.looping,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(ActorDef {
+            kind: self.kind,
+            sprite: self.sprite,
+            rotations: self.rotations,
+            sequences,
+        })
+    }
+}
+
+// ActorDef describes how the entities of one kind are drawn:
+// the sprite sheet, how many viewing angles it has and its animations
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActorDef {
+    pub kind: String,
+    pub sprite: String,
+    pub rotations: u32,
+    pub sequences: Vec<Sequence>,
+}
+
+impl ActorDef {
+    pub fn sequence(&self, name: &str) -> Option<&Sequence> {
+        self.sequences.iter().find(|sequence| sequence.name == name)
+    }
+}
+
+// ActorRegistry maps entity kinds to their actor definitions
+#[derive(Debug, Clone)]
+pub struct ActorRegistry {
+    defs: BTreeMap<String, ActorDef>,
+}
+
+impl Default for ActorRegistry {
+    fn default() -> Self {
+        Self::from_toml(BUILTIN_ACTORS).expect("builtin actors should parse")
+    }
+}
+
+impl ActorRegistry {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read actors {}: {}", path, err))?;
+        Self::from_toml(&source).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    pub fn from_toml(source: &str) -> Result<Self, String> {
+        let file: ActorFile = toml::from_str(source).map_err(|err| err.to_string())?;
+        let mut defs = BTreeMap::new();
+        for raw in file.actor {
+            let kind = raw.kind.clone();
+            let def = raw
+                .into_def()
+                .map_err(|err| format!("actor {}: {}", kind, err))?;
+            if defs.insert(kind.clone(), def).is_some() {
+                return Err(format!("duplicate actor {}", kind));
+            }
+        }
+        Ok(ActorRegistry { defs })
+    }
+
+    pub fn get(&self, kind: &str) -> Option<&ActorDef> {
+        self.defs.get(kind)
+    }
+}
+
+// rotation picks which of the rotations of an actor facing angle is seen
+// from a viewer in the direction to_viewer. 0 is the front and the
+// rotations go clockwise around the actor, each covering an equal slice.
+pub fn rotation(facing: f64, to_viewer: f64, rotations: u32) -> u32 {
+    let slice = TAU / rotations as f64;
+    let relative = (to_viewer - facing).rem_euclid(TAU);
+    (relative / slice).round() as u32 % rotations
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    #[test]
+    fn test_builtin_actors() {
+        let actors = ActorRegistry::default();
+        let guard = actors.get("guard").unwrap();
+        assert_eq!(guard.rotations, 8);
+        assert_eq!(guard.sequences[0].name, "stand");
+        assert_eq!(guard.sequence("walk").unwrap().frames, vec![1, 2, 3, 4]);
+        assert!(actors.get("barrel").is_none());
+    }
+
+    #[test]
+    fn test_from_toml_errors() {
+        let actor = |body: &str| {
+            ActorRegistry::from_toml(&format!(
+                "[[actor]]\nkind = \"dog\"\nsprite = \"dog\"\n{}",
+                body
+            ))
+        };
+        let sequence = "[[actor.sequence]]\nname = \"run\"\nframes = [0, 1]\nduration = 0.1\n";
+        assert!(actor(sequence).is_ok());
+        assert!(actor(&format!("rotations = 4\n{}", sequence))
+            .unwrap_err()
+            .contains("rotations"));
+        assert!(actor("").unwrap_err().contains("at least one sequence"));
+        assert!(actor(&sequence.replace("[0, 1]", "[]"))
+            .unwrap_err()
+            .contains("no frames"));
+        assert!(actor(&sequence.replace("0.1", "0"))
+            .unwrap_err()
+            .contains("positive duration"));
+    }
+
+    #[test]
+    fn test_rotation() {
+        // a viewer straight ahead sees the front, behind it the back
+        assert_eq!(rotation(0.0, 0.0, 8), 0);
+        assert_eq!(rotation(0.0, PI, 8), 4);
+        // y grows downwards, so a quarter turn clockwise is the right side
+        assert_eq!(rotation(0.0, PI / 2.0, 8), 2);
+        assert_eq!(rotation(PI / 2.0, 0.0, 8), 6);
+        // angles round to the nearest rotation and wrap around
+        assert_eq!(rotation(0.0, 0.3, 8), 0);
+        assert_eq!(rotation(0.0, -0.3, 8), 0);
+        assert_eq!(rotation(0.0, 0.5, 8), 1);
+        assert_eq!(rotation(1.0, 2.0, 1), 0);
+    }
+}
@@ -0,0 +1,96 @@
+// Sequence is one animation of an actor, such as standing or walking.
+// Each frame is shown for duration seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sequence {
+    pub name: String,
+    pub frames: Vec<u32>,
+    pub duration: f64,
+    pub looping: bool,
+}
+
+impl Sequence {
+    pub fn length(&self) -> f64 {
+        self.frames.len() as f64 * self.duration
+    }
+
+    // frame_at returns the frame shown time seconds into the sequence,
+    // sequences that do not loop hold their last frame
+    pub fn frame_at(&self, time: f64) -> u32 {
+        let step = (time.max(0.0) / self.duration) as usize;
+        let index = match self.looping {
+            true => step % self.frames.len(),
+            false => step.min(self.frames.len() - 1),
+        };
+        self.frames[index]
+    }
+}
+
+// Animation plays a sequence, its clock is advanced once per frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub sequence: Sequence,
+    time: f64,
+}
+
+impl Animation {
+    pub fn new(sequence: Sequence) -> Self {
+        Animation {
+            sequence,
+            time: 0.0,
+        }
+    }
+
+    pub fn advance(&mut self, delta_time: f64) {
+        self.time += delta_time;
+        // looping sequences wrap their clock so that it keeps its precision
+        if self.sequence.looping {
+            self.time = self.time.rem_euclid(self.sequence.length());
+        }
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.sequence.frame_at(self.time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(looping: bool) -> Sequence {
+        Sequence {
+            name: String::from("walk"),
+            frames: vec![1, 2, 3],
+            duration: 0.25,
+            looping,
+        }
+    }
+
+    #[test]
+    fn test_frame_at() {
+        let walk = sequence(true);
+        assert_eq!(walk.frame_at(0.0), 1);
+        assert_eq!(walk.frame_at(0.3), 2);
+        assert_eq!(walk.frame_at(0.6), 3);
+        assert_eq!(walk.frame_at(0.8), 1);
+        let die = sequence(false);
+        assert_eq!(die.frame_at(0.6), 3);
+        assert_eq!(die.frame_at(10.0), 3);
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut animation = Animation::new(sequence(true));
+        assert_eq!(animation.frame(), 1);
+        for _ in 0..5 {
+            animation.advance(0.1);
+        }
+        assert_eq!(animation.frame(), 3);
+        for _ in 0..101 {
+            animation.advance(0.1);
+        }
+        // 10.6 seconds in, 14 whole loops and 0.1 seconds
+        assert_eq!(animation.frame(), 1);
+        assert!(animation.time < animation.sequence.length());
+    }
+}
@@ -0,0 +1,313 @@
+use crate::{
+    level::level::Entity,
+    texture::manager::{TextureId, TextureKind, TextureManager},
+    window::window::{Resolution, TILE_SIZE},
+};
+
+use super::{
+    actor::{rotation, ActorDef, ActorRegistry},
+    animation::Animation,
+};
+
+// Entities name their sprite with this property, or else by their kind
+pub const SPRITE_PROPERTY: &str = "sprite";
+
+// Actors start playing the sequence named by this property, or else their first one
+pub const SEQUENCE_PROPERTY: &str = "sequence";
+
+// Sprites closer than this to the camera plane are not drawn
+const NEAR_PLANE: f64 = 1.0;
+
+// Sprite is a picture standing in the world at x, y that always faces the player.
+// Like the walls it spans one tile from the floor to the ceiling.
+// Actors show the frame of their animation seen from the angle they are looked at,
+// other sprites always show their first frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprite {
+    pub x: f64,
+    pub y: f64,
+    pub angle: f64,
+    pub texture: TextureId,
+    pub rotations: u32,
+    pub animation: Option<Animation>,
+}
+
+// Projection is where a sprite lands on screen: the frame of its sheet,
+// the column of its centre, its distance along the view direction
+// and its height in pixels, along with where it stands in the world
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    pub x: f64,
+    pub y: f64,
+    pub texture: TextureId,
+    pub frame: u32,
+    pub center_x: f64,
+    pub depth: f64,
+    pub size: f64,
+}
+
+impl Sprite {
+    // from_entities returns the sprites of the entities that have a picture.
+    // Entities of an actor kind use the sheet of the actor. Otherwise a sprite named
+    // by the sprite property must exist and is reported when missing, while entities
+    // whose kind names no sprite such as exits stay invisible.
+    pub fn from_entities(
+        entities: &[Entity],
+        actors: &ActorRegistry,
+        textures: &mut TextureManager,
+    ) -> (Vec<Sprite>, Vec<String>) {
+        let mut sprites = Vec::new();
+        let mut warnings = Vec::new();
+        let require = |textures: &mut TextureManager, name: &str, warnings: &mut Vec<String>| {
+            textures
+                .require(TextureKind::Sprite, name)
+                .unwrap_or_else(|warning| {
+                    warnings.push(warning);
+                    TextureManager::MISSING
+                })
+        };
+        for entity in entities {
+            if let Some(def) = actors.get(&entity.kind) {
+                let texture = require(textures, &def.sprite, &mut warnings);
+                sprites.push(Self::actor(entity, def, texture, &mut warnings));
+                continue;
+            }
+            let texture = match entity.properties.get(SPRITE_PROPERTY) {
+                Some(name) => Some(require(textures, name, &mut warnings)),
+                None => textures.find(TextureKind::Sprite, &entity.kind),
+            };
+            if let Some(texture) = texture {
+                sprites.push(Sprite {
+                    x: entity.x,
+                    y: entity.y,
+                    angle: entity.angle,
+                    texture,
+                    rotations: 1,
+                    animation: None,
+                });
+            }
+        }
+        (sprites, warnings)
+    }
+
+    fn actor(
+        entity: &Entity,
+        def: &ActorDef,
+        texture: TextureId,
+        warnings: &mut Vec<String>,
+    ) -> Sprite {
+        let sequence = match entity.properties.get(SEQUENCE_PROPERTY) {
+            Some(name) => def.sequence(name).unwrap_or_else(|| {
+                warnings.push(format!("actor {} has no sequence {}", def.kind, name));
+                &def.sequences[0]
+            }),
+            None => &def.sequences[0],
+        };
+        Sprite {
+            x: entity.x,
+            y: entity.y,
+            angle: entity.angle,
+            texture,
+            rotations: def.rotations,
+            animation: Some(Animation::new(sequence.clone())),
+        }
+    }
+
+    // advance moves the animation of the sprite forward by delta_time seconds
+    pub fn advance(&mut self, delta_time: f64) {
+        if let Some(animation) = &mut self.animation {
+            animation.advance(delta_time);
+        }
+    }
+
+    // frame is the column of the sheet seen from x, y: the animation frame
+    // followed by one column per rotation
+    pub fn frame(&self, x: f64, y: f64) -> u32 {
+        let pose = self
+            .animation
+            .as_ref()
+            .map_or(0, |animation| animation.frame());
+        let to_viewer = (y - self.y).atan2(x - self.x);
+        pose * self.rotations + rotation(self.angle, to_viewer, self.rotations)
+    }
+
+    // project places the sprite on screen as seen from x, y looking along angle,
+    // using the same projection plane as the walls.
+    // Sprites behind the camera give None.
+    pub fn project(
+        &self,
+        x: f64,
+        y: f64,
+        angle: f64,
+        resolution: &Resolution,
+    ) -> Option<Projection> {
+        let (dx, dy) = (self.x - x, self.y - y);
+        let (sin, cos) = angle.sin_cos();
+        let depth = dx * cos + dy * sin;
+        if depth < NEAR_PLANE {
+            return None;
+        }
+        let side = dy * cos - dx * sin;
+        let distance_proj_plane = resolution.distance_proj_plane();
+        Some(Projection {
+            x: self.x,
+            y: self.y,
+            texture: self.texture,
+            frame: self.frame(x, y),
+            center_x: resolution.width as f64 / 2.0 + side / depth * distance_proj_plane,
+            depth,
+            size: TILE_SIZE as f64 / depth * distance_proj_plane,
+        })
+    }
+}
+
+// project_sprites projects every visible sprite, sorted back to front
+// so that nearer sprites are drawn over farther ones
+pub fn project_sprites(
+    sprites: &[Sprite],
+    x: f64,
+    y: f64,
+    angle: f64,
+    resolution: &Resolution,
+) -> Vec<Projection> {
+    let mut projections: Vec<Projection> = sprites
+        .iter()
+        .filter_map(|sprite| sprite.project(x, y, angle, resolution))
+        .collect();
+    projections.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+    projections
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::texture::manager::shipped;
+
+    fn sprite_at(x: f64, y: f64) -> Sprite {
+        Sprite {
+            x,
+            y,
+            angle: 0.0,
+            texture: TextureId(1),
+            rotations: 1,
+            animation: None,
+        }
+    }
+
+    fn entity(kind: &str, properties: &[(&str, &str)]) -> Entity {
+        Entity {
+            kind: String::from(kind),
+            name: String::new(),
+            x: 96.0,
+            y: 32.0,
+            angle: 0.0,
+            properties: properties
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_project() {
+        let resolution = Resolution::new(320, 200);
+        let tile = TILE_SIZE as f64;
+        let ahead = sprite_at(2.0 * tile, 0.0)
+            .project(0.0, 0.0, 0.0, &resolution)
+            .unwrap();
+        assert_eq!(ahead.center_x, 160.0);
+        assert_eq!(ahead.depth, 2.0 * tile);
+        assert!((ahead.size - resolution.distance_proj_plane() / 2.0).abs() < 1e-9);
+
+        // y grows downwards, so a sprite at +y is to the right when facing east
+        let right = sprite_at(tile, tile)
+            .project(0.0, 0.0, 0.0, &resolution)
+            .unwrap();
+        assert!(right.center_x > 160.0);
+        let left = sprite_at(tile, tile)
+            .project(0.0, 0.0, PI / 2.0, &resolution)
+            .unwrap();
+        assert!(left.center_x < 160.0);
+
+        assert_eq!(
+            sprite_at(-tile, 0.0).project(0.0, 0.0, 0.0, &resolution),
+            None
+        );
+    }
+
+    #[test]
+    fn test_project_sprites_back_to_front() {
+        let resolution = Resolution::new(320, 200);
+        let sprites = [
+            sprite_at(100.0, 0.0),
+            sprite_at(300.0, 10.0),
+            sprite_at(-50.0, 0.0),
+            sprite_at(200.0, -10.0),
+        ];
+        let depths: Vec<f64> = project_sprites(&sprites, 0.0, 0.0, 0.0, &resolution)
+            .iter()
+            .map(|projection| projection.depth)
+            .collect();
+        assert_eq!(depths, vec![300.0, 200.0, 100.0]);
+    }
+
+    #[test]
+    fn test_from_entities() {
+        let mut textures = shipped();
+        let entities = [
+            entity("barrel", &[]),
+            entity("exit", &[]),
+            entity("prop", &[(SPRITE_PROPERTY, "pillar")]),
+            entity("prop", &[(SPRITE_PROPERTY, "statue")]),
+        ];
+        let (sprites, warnings) =
+            Sprite::from_entities(&entities, &ActorRegistry::default(), &mut textures);
+        assert_eq!(sprites.len(), 3);
+        assert_eq!(
+            sprites[0].texture,
+            textures.id(TextureKind::Sprite, "barrel")
+        );
+        assert_eq!((sprites[0].x, sprites[0].y), (96.0, 32.0));
+        assert_eq!(
+            sprites[1].texture,
+            textures.id(TextureKind::Sprite, "pillar")
+        );
+        assert_eq!(sprites[2].texture, TextureManager::MISSING);
+        assert_eq!(
+            warnings,
+            vec![String::from("sprite texture statue not found")]
+        );
+        assert!(sprites.iter().all(|sprite| sprite.animation.is_none()));
+    }
+
+    #[test]
+    fn test_actor_frames() {
+        let mut textures = shipped();
+        let entities = [
+            entity("guard", &[]),
+            entity("guard", &[(SEQUENCE_PROPERTY, "walk")]),
+            entity("guard", &[(SEQUENCE_PROPERTY, "dance")]),
+        ];
+        let (mut sprites, warnings) =
+            Sprite::from_entities(&entities, &ActorRegistry::default(), &mut textures);
+        assert_eq!(
+            warnings,
+            vec![String::from("actor guard has no sequence dance")]
+        );
+        assert_eq!(
+            sprites[0].animation.as_ref().unwrap().sequence.name,
+            "stand"
+        );
+
+        // the guard faces east, pose 1 of the walk is drawn from columns 8 to 15
+        let walker = &mut sprites[1];
+        assert_eq!(walker.rotations, 8);
+        assert_eq!(walker.frame(200.0, 32.0), 8);
+        assert_eq!(walker.frame(96.0, 100.0), 10);
+        assert_eq!(walker.frame(0.0, 32.0), 12);
+        walker.advance(0.2);
+        assert_eq!(walker.frame(200.0, 32.0), 16);
+    }
+}
@@ -0,0 +1,233 @@
+use std::{fs, path::Path};
+
+use png::{BitDepth, ColorType, Decoder, Transformations};
+
+use super::texture::Texture;
+
+// load_file decodes a PNG or BMP file, picked by its extension
+pub fn load_file(path: &Path) -> Result<Texture, String> {
+    let bytes =
+        fs::read(path).map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    let texture = match extension.as_deref() {
+        Some("png") => decode_png(&bytes),
+        Some("bmp") => decode_bmp(&bytes),
+        _ => Err(String::from("unsupported image format")),
+    };
+    texture.map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+// is_image tells whether load_file knows how to read the file
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png") || ext.eq_ignore_ascii_case("bmp"))
+}
+
+fn argb(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    (a as u32) << 24 | (r as u32) << 16 | (g as u32) << 8 | b as u32
+}
+
+// decode_png expands palettes, grey levels and 16 bit channels
+// so that every pixel ends up as 8 bit RGB or RGBA
+pub fn decode_png(bytes: &[u8]) -> Result<Texture, String> {
+    let mut decoder = Decoder::new(bytes);
+    decoder.set_transformations(Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|err| err.to_string())?;
+    let data = &buffer[..info.buffer_size()];
+
+    let pixels = match (info.color_type, info.bit_depth) {
+        (ColorType::Rgba, BitDepth::Eight) => data
+            .chunks_exact(4)
+            .map(|p| argb(p[0], p[1], p[2], p[3]))
+            .collect(),
+        (ColorType::Rgb, BitDepth::Eight) => data
+            .chunks_exact(3)
+            .map(|p| argb(p[0], p[1], p[2], 0xFF))
+            .collect(),
+        (ColorType::GrayscaleAlpha, BitDepth::Eight) => data
+            .chunks_exact(2)
+            .map(|p| argb(p[0], p[0], p[0], p[1]))
+            .collect(),
+        (ColorType::Grayscale, BitDepth::Eight) => {
+            data.iter().map(|v| argb(*v, *v, *v, 0xFF)).collect()
+        }
+        (color_type, bit_depth) => {
+            return Err(format!(
+                "unsupported PNG pixel format {:?} {:?}",
+                color_type, bit_depth
+            ))
+        }
+    };
+    Texture::new(info.width, info.height, pixels)
+}
+
+// decode_bmp reads uncompressed Windows bitmaps
+// with 8 bit palettes or 24 and 32 bit pixels
+pub fn decode_bmp(bytes: &[u8]) -> Result<Texture, String> {
+    let u16_at = |offset: usize| -> Result<u16, String> {
+        bytes
+            .get(offset..offset + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .ok_or_else(|| String::from("truncated BMP"))
+    };
+    let u32_at = |offset: usize| -> Result<u32, String> {
+        bytes
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| String::from("truncated BMP"))
+    };
+
+    if bytes.get(0..2) != Some(b"BM") {
+        return Err(String::from("not a BMP file"));
+    }
+    let data_offset = u32_at(10)? as usize;
+    let header_size = u32_at(14)? as usize;
+    if header_size < 40 {
+        return Err(format!("unsupported BMP header of {} bytes", header_size));
+    }
+    let width = u32_at(18)? as i32;
+    let height = u32_at(22)? as i32;
+    let bits = u16_at(28)?;
+    let compression = u32_at(30)?;
+    // 32 bit images may use bit fields, in which case the usual BGRA order is assumed
+    if compression != 0 && !(compression == 3 && bits == 32) {
+        return Err(format!("unsupported BMP compression {}", compression));
+    }
+    if width <= 0 || height == 0 {
+        return Err(format!("invalid BMP size {}x{}", width, height));
+    }
+
+    let palette = match bits {
+        8 => {
+            let colors = match u32_at(46)? {
+                0 => 256,
+                n => n as usize,
+            };
+            let start = 14 + header_size;
+            (0..colors)
+                .map(|i| {
+                    let b = bytes
+                        .get(start + i * 4..start + i * 4 + 3)
+                        .ok_or_else(|| String::from("truncated BMP palette"))?;
+                    Ok(argb(b[2], b[1], b[0], 0xFF))
+                })
+                .collect::<Result<Vec<u32>, String>>()?
+        }
+        24 | 32 => Vec::new(),
+        _ => return Err(format!("unsupported BMP depth of {} bits", bits)),
+    };
+
+    let (width, rows) = (width as usize, height.unsigned_abs() as usize);
+    let bytes_per_pixel = bits as usize / 8;
+    // rows are padded to four bytes and stored bottom up unless the height is negative
+    let stride = (width * bytes_per_pixel).div_ceil(4) * 4;
+    // the size comes from the header, the data must hold it before anything is allocated
+    let end = (rows - 1)
+        .checked_mul(stride)
+        .and_then(|size| size.checked_add(width * bytes_per_pixel))
+        .and_then(|size| size.checked_add(data_offset));
+    if end.is_none_or(|end| end > bytes.len()) {
+        return Err(String::from("truncated BMP pixel data"));
+    }
+    let mut pixels = Vec::with_capacity(width * rows);
+    for y in 0..rows {
+        let row = if height > 0 { rows - 1 - y } else { y };
+        let start = data_offset + row * stride;
+        let data = bytes
+            .get(start..start + width * bytes_per_pixel)
+            .ok_or_else(|| String::from("truncated BMP pixel data"))?;
+        for p in data.chunks_exact(bytes_per_pixel) {
+            pixels.push(match bits {
+                8 => *palette
+                    .get(p[0] as usize)
+                    .ok_or_else(|| format!("BMP palette index {} out of range", p[0]))?,
+                24 => argb(p[2], p[1], p[0], 0xFF),
+                _ => argb(p[2], p[1], p[0], p[3]),
+            });
+        }
+    }
+    Texture::new(width as u32, rows as u32, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // bmp writes a bottom up 24 bit bitmap from rows of RGB pixels
+    fn bmp(rows: &[Vec<[u8; 3]>]) -> Vec<u8> {
+        let width = rows[0].len();
+        let stride = (width * 3).div_ceil(4) * 4;
+        let size = 54 + stride * rows.len();
+        let mut bytes = Vec::with_capacity(size);
+        bytes.extend_from_slice(b"BM");
+        bytes.extend_from_slice(&(size as u32).to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&54u32.to_le_bytes());
+        bytes.extend_from_slice(&40u32.to_le_bytes());
+        bytes.extend_from_slice(&(width as i32).to_le_bytes());
+        bytes.extend_from_slice(&(rows.len() as i32).to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&24u16.to_le_bytes());
+        bytes.extend_from_slice(&[0; 24]);
+        for row in rows.iter().rev() {
+            for [r, g, b] in row {
+                bytes.extend_from_slice(&[*b, *g, *r]);
+            }
+            bytes.resize(bytes.len() + stride - width * 3, 0);
+        }
+        bytes
+    }
+
+    fn png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(ColorType::Rgba);
+        encoder.set_depth(BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(rgba).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_decode_bmp() {
+        let bytes = bmp(&[
+            vec![[255, 0, 0], [0, 255, 0], [0, 0, 255]],
+            vec![[1, 2, 3], [4, 5, 6], [7, 8, 9]],
+        ]);
+        let texture = decode_bmp(&bytes).unwrap();
+        assert_eq!((texture.width, texture.height), (3, 2));
+        assert_eq!(texture.pixels[0], 0xFFFF0000);
+        assert_eq!(texture.pixels[2], 0xFF0000FF);
+        assert_eq!(texture.pixels[3 + 1], 0xFF040506);
+    }
+
+    #[test]
+    fn test_decode_bmp_errors() {
+        assert!(decode_bmp(b"PNG").is_err());
+        let mut bytes = bmp(&vec![vec![[0, 0, 0]; 2]; 2]);
+        bytes.truncate(60);
+        assert_eq!(decode_bmp(&bytes).unwrap_err(), "truncated BMP pixel data");
+
+        // a huge size in the header of a small file is not allocated
+        let mut bytes = bmp(&vec![vec![[0, 0, 0]; 2]; 2]);
+        bytes[18..22].copy_from_slice(&i32::MAX.to_le_bytes());
+        bytes[22..26].copy_from_slice(&i32::MIN.to_le_bytes());
+        assert_eq!(decode_bmp(&bytes).unwrap_err(), "truncated BMP pixel data");
+    }
+
+    #[test]
+    fn test_decode_png() {
+        let texture = decode_png(&png(2, 1, &[10, 20, 30, 255, 1, 2, 3, 128])).unwrap();
+        assert_eq!(texture.pixels, vec![0xFF0A141E, 0x80010203]);
+        assert!(decode_png(b"not a png").is_err());
+    }
+}
@@ -0,0 +1,359 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::tile::registry::TileRegistry;
+
+use super::{
+    image::{is_image, load_file},
+    texture::Texture,
+};
+
+// Wall textures are square tiles of this size, sprite frames too
+pub const TEXTURE_SIZE: u32 = 64;
+
+// Size and colours of the squares drawn for missing textures
+const CHECKER_SIZE: u32 = 8;
+const CHECKER_COLORS: [u32; 2] = [0xFFFF00FF, 0xFF000000];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureKind {
+    Wall,
+    Sprite,
+    Ui,
+    Sky,
+}
+
+impl TextureKind {
+    const ALL: [TextureKind; 4] = [
+        TextureKind::Wall,
+        TextureKind::Sprite,
+        TextureKind::Ui,
+        TextureKind::Sky,
+    ];
+
+    // dir is the sub directory of the asset directory holding the kind
+    pub fn dir(&self) -> &'static str {
+        match self {
+            TextureKind::Wall => "walls",
+            TextureKind::Sprite => "sprites",
+            TextureKind::Ui => "ui",
+            TextureKind::Sky => "sky",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextureKind::Wall => "wall",
+            TextureKind::Sprite => "sprite",
+            TextureKind::Ui => "ui",
+            TextureKind::Sky => "sky",
+        }
+    }
+
+    // check_size describes what is wrong with the size of a texture of this kind,
+    // walls are single tiles and sprites strips of square frames
+    fn check_size(&self, width: u32, height: u32) -> Option<String> {
+        match self {
+            TextureKind::Wall if (width, height) != (TEXTURE_SIZE, TEXTURE_SIZE) => Some(format!(
+                "is {}x{}, wall textures should be {}x{}",
+                width, height, TEXTURE_SIZE, TEXTURE_SIZE
+            )),
+            TextureKind::Sprite
+                if height != TEXTURE_SIZE || !width.is_multiple_of(TEXTURE_SIZE) =>
+            {
+                Some(format!(
+                    "is {}x{}, sprites should be {} high with frames {} wide",
+                    width, height, TEXTURE_SIZE, TEXTURE_SIZE
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+// TextureId is the index of a texture in the atlas,
+// it does not change once the texture is added
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureId(pub u32);
+
+// TextureView borrows one texture out of the atlas
+#[derive(Debug, Clone, Copy)]
+pub struct TextureView<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: &'a [u32],
+}
+
+impl TextureView<'_> {
+    // texel wraps coordinates past the edges around
+    pub fn texel(&self, x: u32, y: u32) -> u32 {
+        self.pixels[((y % self.height) * self.width + x % self.width) as usize]
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    width: u32,
+    height: u32,
+    offset: usize,
+}
+
+// TextureManager keeps every texture in one contiguous ARGB8888 atlas.
+// The checkerboard drawn for missing textures always has the id 0.
+#[derive(Debug, Clone)]
+pub struct TextureManager {
+    atlas: Vec<u32>,
+    entries: Vec<Entry>,
+    ids: HashMap<TextureKind, HashMap<String, TextureId>>,
+}
+
+impl Default for TextureManager {
+    fn default() -> Self {
+        let mut manager = TextureManager {
+            atlas: Vec::new(),
+            entries: Vec::new(),
+            ids: HashMap::new(),
+        };
+        manager.push(&checkerboard());
+        manager
+    }
+}
+
+impl TextureManager {
+    pub const MISSING: TextureId = TextureId(0);
+
+    fn push(&mut self, texture: &Texture) -> TextureId {
+        let id = TextureId(self.entries.len() as u32);
+        self.entries.push(Entry {
+            width: texture.width,
+            height: texture.height,
+            offset: self.atlas.len(),
+        });
+        self.atlas.extend_from_slice(&texture.pixels);
+        id
+    }
+
+    pub fn insert(
+        &mut self,
+        kind: TextureKind,
+        name: &str,
+        texture: &Texture,
+    ) -> Result<TextureId, String> {
+        if self.find(kind, name).is_some() {
+            return Err(format!("duplicate texture {}/{}", kind.dir(), name));
+        }
+        let id = self.push(texture);
+        self.ids
+            .entry(kind)
+            .or_default()
+            .insert(name.to_string(), id);
+        Ok(id)
+    }
+
+    // load_dir adds the PNG and BMP files found in the walls, sprites, ui and sky
+    // sub directories of root, named after their file name without extension.
+    // Unreadable files are errors, files of the wrong size are returned as warnings.
+    pub fn load_dir(&mut self, root: &Path) -> Result<Vec<String>, String> {
+        let mut warnings = Vec::new();
+        for kind in TextureKind::ALL {
+            let dir = root.join(kind.dir());
+            if !dir.is_dir() {
+                continue;
+            }
+            let mut paths: Vec<_> = fs::read_dir(&dir)
+                .map_err(|err| format!("failed to read {}: {}", dir.display(), err))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| is_image(path))
+                .collect();
+            // sorting keeps ids the same from one run to the next
+            paths.sort();
+
+            for path in paths {
+                let texture = load_file(&path)?;
+                if let Some(problem) = kind.check_size(texture.width, texture.height) {
+                    warnings.push(format!("{} {}", path.display(), problem));
+                }
+                let name = path
+                    .file_stem()
+                    .map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+                self.insert(kind, &name, &texture)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?;
+            }
+        }
+        Ok(warnings)
+    }
+
+    // require loads the texture with the given name. A texture that cannot be found
+    // is reported once and the checkerboard is used in its place.
+    pub fn require(&mut self, kind: TextureKind, name: &str) -> Result<TextureId, String> {
+        if let Some(id) = self.find(kind, name) {
+            return Ok(id);
+        }
+        self.ids
+            .entry(kind)
+            .or_default()
+            .insert(name.to_string(), Self::MISSING);
+        Err(format!("{} texture {} not found", kind.name(), name))
+    }
+
+    // load_tile_textures requires every texture the tiles refer to,
+    // returning the ones that were not found as warnings
+    pub fn load_tile_textures(&mut self, tiles: &TileRegistry) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for def in tiles.iter() {
+            let names = [
+                &def.textures.north,
+                &def.textures.south,
+                &def.textures.east,
+                &def.textures.west,
+                &def.floor,
+                &def.ceiling,
+            ];
+            for name in names.into_iter().flatten() {
+                if let Err(warning) = self.require(TextureKind::Wall, name) {
+                    warnings.push(warning);
+                }
+            }
+        }
+        warnings
+    }
+
+    pub fn find(&self, kind: TextureKind, name: &str) -> Option<TextureId> {
+        self.ids.get(&kind)?.get(name).copied()
+    }
+
+    // id returns the texture with the given name or the checkerboard
+    pub fn id(&self, kind: TextureKind, name: &str) -> TextureId {
+        self.find(kind, name).unwrap_or(Self::MISSING)
+    }
+
+    pub fn get(&self, id: TextureId) -> TextureView<'_> {
+        let entry = self
+            .entries
+            .get(id.0 as usize)
+            .unwrap_or(&self.entries[Self::MISSING.0 as usize]);
+        TextureView {
+            width: entry.width,
+            height: entry.height,
+            pixels: &self.atlas[entry.offset..entry.offset + (entry.width * entry.height) as usize],
+        }
+    }
+}
+
+// shipped returns a manager holding the textures of the asset directory
+#[cfg(test)]
+pub fn shipped() -> TextureManager {
+    let mut manager = TextureManager::default();
+    let root = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/textures");
+    assert!(manager.load_dir(Path::new(root)).unwrap().is_empty());
+    manager
+}
+
+fn checkerboard() -> Texture {
+    Texture::from_fn(TEXTURE_SIZE, TEXTURE_SIZE, |x, y| {
+        CHECKER_COLORS[((x / CHECKER_SIZE + y / CHECKER_SIZE) % 2) as usize]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_png(path: &Path, width: u32, height: u32, rgb: [u8; 3]) {
+        let file = fs::File::create(path).unwrap();
+        let mut encoder = png::Encoder::new(file, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let data: Vec<u8> = rgb.repeat((width * height) as usize);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&data)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let root = std::env::temp_dir().join(format!("raycasting_load_dir_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("walls")).unwrap();
+        fs::create_dir_all(root.join("sprites")).unwrap();
+        write_png(&root.join("walls/stone.png"), 64, 64, [10, 20, 30]);
+        write_png(&root.join("walls/tiny.png"), 16, 16, [1, 1, 1]);
+        write_png(&root.join("sprites/lamp.png"), 128, 64, [5, 5, 5]);
+        fs::write(root.join("walls/notes.txt"), "not an image").unwrap();
+
+        let mut manager = TextureManager::default();
+        let warnings = manager.load_dir(&root).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("tiny.png is 16x16"));
+
+        let stone = manager.id(TextureKind::Wall, "stone");
+        assert_eq!(stone, TextureId(1));
+        assert_eq!(manager.get(stone).texel(3, 3), 0xFF0A141E);
+        let lamp = manager.get(manager.id(TextureKind::Sprite, "lamp"));
+        assert_eq!((lamp.width, lamp.height), (128, 64));
+
+        fs::write(root.join("walls/broken.png"), "not a png").unwrap();
+        let err = TextureManager::default().load_dir(&root).unwrap_err();
+        assert!(err.contains("broken.png"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_missing_textures_use_the_checkerboard() {
+        let mut manager = shipped();
+        let tiles = TileRegistry::from_toml(
+            "[[tile]]\nid = 1\nname = \"Odd\"\ntexture = \"marble\"\n\n\
+             [[tile]]\nid = 2\nname = \"Brick\"\ntexture = \"redbrick\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            manager.load_tile_textures(&tiles),
+            vec![String::from("wall texture marble not found")]
+        );
+        assert_eq!(
+            manager.require(TextureKind::Wall, "marble"),
+            Ok(TextureManager::MISSING)
+        );
+        assert_eq!(
+            manager.id(TextureKind::Wall, "marble"),
+            TextureManager::MISSING
+        );
+        assert_ne!(
+            manager.id(TextureKind::Wall, "redbrick"),
+            TextureManager::MISSING
+        );
+
+        let missing = manager.get(TextureManager::MISSING);
+        assert_eq!(missing.texel(0, 0), CHECKER_COLORS[0]);
+        assert_eq!(missing.texel(CHECKER_SIZE, 0), CHECKER_COLORS[1]);
+    }
+
+    #[test]
+    fn test_builtin_tiles_have_shipped_textures() {
+        let mut manager = shipped();
+        assert!(manager
+            .load_tile_textures(&TileRegistry::default())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_ids_are_stable() {
+        let mut manager = TextureManager::default();
+        let red = Texture::from_fn(2, 2, |_, _| 0xFFFF0000);
+        let a = manager.insert(TextureKind::Ui, "a", &red).unwrap();
+        let b = manager.insert(TextureKind::Ui, "b", &red).unwrap();
+        assert_eq!((a, b), (TextureId(1), TextureId(2)));
+        assert!(manager.insert(TextureKind::Ui, "a", &red).is_err());
+        assert_eq!(manager.get(a).pixels, &[0xFFFF0000; 4]);
+    }
+
+    #[test]
+    fn test_texel_wraps() {
+        let mut manager = TextureManager::default();
+        let texture = Texture::from_fn(4, 2, |x, y| y * 4 + x);
+        let id = manager.insert(TextureKind::Ui, "ramp", &texture).unwrap();
+        assert_eq!(manager.get(id).texel(3, 1), 7);
+        assert_eq!(manager.get(id).texel(5, 2), 1);
+    }
+}
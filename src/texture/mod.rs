@@ -0,0 +1,4 @@
+pub mod image;
+pub mod manager;
+pub mod surfaces;
+pub mod texture;
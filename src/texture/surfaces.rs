@@ -0,0 +1,174 @@
+use crate::{
+    level::level::{Sky, Surface},
+    tile::{registry::TileRegistry, tile::TileId},
+};
+
+use super::manager::{TextureId, TextureKind, TextureManager};
+
+// SurfaceTextures resolves the floor and ceiling texture of every tile
+// once per level, so that drawing a pixel is a plain lookup.
+// Tiles without textures of their own use the ones of the level.
+// The ceiling is open to the sky over sky tiles, or everywhere when the level says so.
+#[derive(Debug, Clone)]
+pub struct SurfaceTextures {
+    floors: Vec<Option<TextureId>>,
+    ceilings: Vec<Option<TextureId>>,
+    level_floor: Option<TextureId>,
+    level_ceiling: Option<TextureId>,
+    open: Vec<bool>,
+    sky: TextureId,
+    sky_everywhere: bool,
+}
+
+impl SurfaceTextures {
+    // new returns the textures along with warnings for the ones not found
+    pub fn new(
+        tiles: &TileRegistry,
+        floor: &Surface,
+        ceiling: &Surface,
+        sky: Option<&Sky>,
+        textures: &mut TextureManager,
+    ) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut require = |kind: TextureKind, name: &str| {
+            textures.require(kind, name).unwrap_or_else(|warning| {
+                warnings.push(warning);
+                TextureManager::MISSING
+            })
+        };
+        let mut wall = |name: Option<&String>| name.map(|name| require(TextureKind::Wall, name));
+
+        let size = tiles
+            .iter()
+            .map(|def| def.id.0 as usize + 1)
+            .max()
+            .unwrap_or(0);
+        let mut surfaces = SurfaceTextures {
+            floors: vec![None; size],
+            ceilings: vec![None; size],
+            level_floor: wall(floor.texture.as_ref()),
+            level_ceiling: wall(ceiling.texture.as_ref()),
+            open: vec![false; size],
+            sky: TextureManager::MISSING,
+            sky_everywhere: sky.is_some_and(|sky| sky.everywhere),
+        };
+        for def in tiles.iter() {
+            surfaces.floors[def.id.0 as usize] = wall(def.floor.as_ref());
+            surfaces.ceilings[def.id.0 as usize] = wall(def.ceiling.as_ref());
+            surfaces.open[def.id.0 as usize] = def.sky;
+        }
+
+        // the sky is only loaded when some of the level can see it
+        if surfaces.sky_everywhere || surfaces.open.contains(&true) {
+            let sky = sky.cloned().unwrap_or_default();
+            surfaces.sky = require(TextureKind::Sky, &sky.texture);
+        }
+        (surfaces, warnings)
+    }
+
+    pub fn floor(&self, tile: Option<TileId>) -> Option<TextureId> {
+        Self::lookup(&self.floors, tile).or(self.level_floor)
+    }
+
+    pub fn ceiling(&self, tile: Option<TileId>) -> Option<TextureId> {
+        Self::lookup(&self.ceilings, tile).or(self.level_ceiling)
+    }
+
+    // sky returns the sky texture when the ceiling over the tile is open
+    pub fn sky(&self, tile: Option<TileId>) -> Option<TextureId> {
+        let open = tile.is_some_and(|tile| self.open.get(tile.0 as usize) == Some(&true));
+        (self.sky_everywhere || open).then_some(self.sky)
+    }
+
+    fn lookup(textures: &[Option<TextureId>], tile: Option<TileId>) -> Option<TextureId> {
+        textures.get(tile?.0 as usize).copied().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{level::level::DEFAULT_SKY_TEXTURE, texture::manager::shipped};
+
+    #[test]
+    fn test_tile_textures_override_the_level() {
+        let tiles = TileRegistry::default();
+        let mut textures = shipped();
+        let floor = Surface {
+            color: 0,
+            texture: Some(String::from("flagstone")),
+        };
+        let (surfaces, warnings) =
+            SurfaceTextures::new(&tiles, &floor, &Surface::color(0), None, &mut textures);
+        assert!(warnings.is_empty());
+
+        let flagstone = textures.find(TextureKind::Wall, "flagstone");
+        let carpet = textures.find(TextureKind::Wall, "carpet");
+        assert!(flagstone.is_some() && carpet.is_some());
+        assert_eq!(surfaces.floor(Some(TileId::EMPTY)), flagstone);
+        assert_eq!(surfaces.floor(Some(TileId(10))), carpet);
+        assert_eq!(surfaces.floor(None), flagstone);
+        assert_eq!(surfaces.ceiling(Some(TileId::EMPTY)), None);
+        assert_eq!(
+            surfaces.ceiling(Some(TileId(11))),
+            textures.find(TextureKind::Wall, "panels")
+        );
+    }
+
+    #[test]
+    fn test_missing_level_texture() {
+        let mut textures = shipped();
+        let ceiling = Surface {
+            color: 0,
+            texture: Some(String::from("clouds")),
+        };
+        let (surfaces, warnings) = SurfaceTextures::new(
+            &TileRegistry::default(),
+            &Surface::color(0),
+            &ceiling,
+            None,
+            &mut textures,
+        );
+        assert_eq!(
+            warnings,
+            vec![String::from("wall texture clouds not found")]
+        );
+        assert_eq!(surfaces.ceiling(None), Some(TextureManager::MISSING));
+    }
+
+    #[test]
+    fn test_sky() {
+        let tiles = TileRegistry::default();
+        let mut textures = shipped();
+        let (surfaces, warnings) = SurfaceTextures::new(
+            &tiles,
+            &Surface::color(0),
+            &Surface::color(0),
+            None,
+            &mut textures,
+        );
+        assert!(warnings.is_empty());
+        let sky = textures.find(TextureKind::Sky, DEFAULT_SKY_TEXTURE);
+        assert!(sky.is_some());
+        assert_eq!(surfaces.sky(Some(TileId(12))), sky);
+        assert_eq!(surfaces.sky(Some(TileId::EMPTY)), None);
+        assert_eq!(surfaces.sky(None), None);
+
+        let everywhere = Sky {
+            texture: String::from("dusk"),
+            everywhere: true,
+        };
+        let (surfaces, warnings) = SurfaceTextures::new(
+            &tiles,
+            &Surface::color(0),
+            &Surface::color(0),
+            Some(&everywhere),
+            &mut textures,
+        );
+        assert_eq!(warnings, vec![String::from("sky texture dusk not found")]);
+        assert_eq!(
+            surfaces.sky(Some(TileId::EMPTY)),
+            Some(TextureManager::MISSING)
+        );
+    }
+}
@@ -0,0 +1,42 @@
+// Texture is an image in the ARGB8888 layout of the color buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u32>,
+}
+
+impl Texture {
+    pub fn new(width: u32, height: u32, pixels: Vec<u32>) -> Result<Self, String> {
+        if width == 0 || height == 0 {
+            return Err(format!("image {}x{} is empty", width, height));
+        }
+        if pixels.len() != (width * height) as usize {
+            return Err(format!(
+                "image {}x{} needs {} pixels, got {}",
+                width,
+                height,
+                width * height,
+                pixels.len()
+            ));
+        }
+        Ok(Texture {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    // from_fn builds a texture by calling texel for every x, y
+    pub fn from_fn(width: u32, height: u32, texel: impl Fn(u32, u32) -> u32) -> Self {
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| texel(x, y))
+            .collect();
+        Texture {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
@@ -0,0 +1,434 @@
+use std::{collections::BTreeMap, fs};
+
+use serde::Deserialize;
+
+use crate::utils::color::parse_color;
+
+use super::tile::{FaceTextures, Segment, TileDef, TileId};
+
+const BUILTIN_TILES: &str = include_str!("../../assets/tiles.toml");
+
+// Colour used on the minimap for ids missing from the registry
+const UNKNOWN_TILE_COLOR: u32 = 0xFFFF00FF;
+// Highest floor a tile can raise, in storeys, leaving room to stand under the ceiling
+const MAX_FLOOR_HEIGHT: f64 = 0.375;
+
+#[derive(Deserialize)]
+struct TileFile {
+    #[serde(default)]
+    tile: Vec<RawTileDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTileDef {
+    id: u16,
+    name: String,
+    symbol: Option<char>,
+    // tiles with segments are passable and see through unless told otherwise
+    solid: Option<bool>,
+    blocks_sight: Option<bool>,
+    #[serde(default)]
+    exit: bool,
+    #[serde(default)]
+    door: bool,
+    #[serde(default)]
+    secret: bool,
+    // segments as [x1, y1, x2, y2] in fractions of the tile
+    #[serde(default)]
+    segments: Vec<[f64; 4]>,
+    #[serde(default = "default_height")]
+    height: f64,
+    #[serde(default)]
+    floor_height: f64,
+    #[serde(default)]
+    see_through: bool,
+    #[serde(default)]
+    portal: bool,
+    #[serde(default)]
+    mirror: bool,
+    tint: Option<String>,
+    // texture applies to every face not listed in textures
+    texture: Option<String>,
+    #[serde(default)]
+    textures: RawFaceTextures,
+    // floor and ceiling textures drawn over and under passable tiles
+    floor: Option<String>,
+    ceiling: Option<String>,
+    #[serde(default)]
+    sky: bool,
+    minimap_color: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawFaceTextures {
+    north: Option<String>,
+    south: Option<String>,
+    east: Option<String>,
+    west: Option<String>,
+}
+
+fn default_height() -> f64 {
+    1.0
+}
+
+impl RawTileDef {
+    fn into_def(self) -> Result<TileDef, String> {
+        let solid = self.solid.unwrap_or(self.segments.is_empty());
+        let blocks_sight = self.blocks_sight.unwrap_or(self.segments.is_empty());
+        let minimap_color = match &self.minimap_color {
+            Some(color) => {
+                parse_color(color).map_err(|err| format!("tile {}: {}", self.id, err))?
+            }
+            None if solid || !self.segments.is_empty() => 0xFFFFFFFF,
+            None => 0xFF000000,
+        };
+        let segments = self
+            .segments
+            .iter()
+            .map(|&[x1, y1, x2, y2]| {
+                if [x1, y1, x2, y2].iter().any(|v| !(0.0..=1.0).contains(v)) {
+                    return Err(format!("tile {}: segment outside of the tile", self.id));
+                }
+                if (x1, y1) == (x2, y2) {
+                    return Err(format!("tile {}: segment has no length", self.id));
+                }
+                Ok(Segment { x1, y1, x2, y2 })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if !(self.height > 0.0 && self.height.is_finite()) {
+            return Err(format!(
+                "tile {}: height must be positive and finite",
+                self.id
+            ));
+        }
+        // raised floors are stood on, what cannot be walked into has none
+        if !(0.0..=MAX_FLOOR_HEIGHT).contains(&self.floor_height) {
+            return Err(format!(
+                "tile {}: floor height must be between 0 and {}",
+                self.id, MAX_FLOOR_HEIGHT
+            ));
+        }
+        if self.floor_height > 0.0
+            && (solid || blocks_sight || self.door || !self.segments.is_empty())
+        {
+            return Err(format!(
+                "tile {}: only open tiles can have a floor height",
+                self.id
+            ));
+        }
+        let tint = match &self.tint {
+            Some(tint) => {
+                Some(parse_color(tint).map_err(|err| format!("tile {}: {}", self.id, err))?)
+            }
+            None => None,
+        };
+        let all = self.texture;
+        let face = |texture: Option<String>| texture.or_else(|| all.clone());
+
+        Ok(TileDef {
+            id: TileId(self.id),
+            name: self.name,
+            symbol: self.symbol,
+            solid,
+            blocks_sight,
+            exit: self.exit,
+            door: self.door,
+            secret: self.secret,
+            segments,
+            height: self.height,
+            floor_height: self.floor_height,
+            see_through: self.see_through,
+            portal: self.portal,
+            mirror: self.mirror,
+            tint,
+            textures: FaceTextures {
+                north: face(self.textures.north),
+                south: face(self.textures.south),
+                east: face(self.textures.east),
+                west: face(self.textures.west),
+            },
+            floor: self.floor,
+            ceiling: self.ceiling,
+            sky: self.sky,
+            minimap_color,
+        })
+    }
+}
+
+// TileRegistry maps tile ids to their definitions.
+// Ids without a definition are treated as solid, opaque walls.
+#[derive(Debug, Clone)]
+pub struct TileRegistry {
+    defs: BTreeMap<TileId, TileDef>,
+}
+
+impl Default for TileRegistry {
+    fn default() -> Self {
+        Self::from_toml(BUILTIN_TILES).expect("builtin tiles should parse")
+    }
+}
+
+impl TileRegistry {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read tiles {}: {}", path, err))?;
+        Self::from_toml(&source).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    pub fn from_toml(source: &str) -> Result<Self, String> {
+        let file: TileFile = toml::from_str(source).map_err(|err| err.to_string())?;
+        let mut registry = TileRegistry {
+            defs: BTreeMap::new(),
+        };
+        for raw in file.tile {
+            registry.insert(raw.into_def()?)?;
+        }
+
+        if !registry.defs.contains_key(&TileId::EMPTY) {
+            registry.insert(TileDef {
+                id: TileId::EMPTY,
+                name: String::from("Empty"),
+                symbol: Some('.'),
+                solid: false,
+                blocks_sight: false,
+                exit: false,
+                door: false,
+                secret: false,
+                segments: Vec::new(),
+                height: 1.0,
+                floor_height: 0.0,
+                see_through: false,
+                portal: false,
+                mirror: false,
+                tint: None,
+                textures: FaceTextures::default(),
+                floor: None,
+                ceiling: None,
+                sky: false,
+                minimap_color: 0xFF000000,
+            })?;
+        }
+        Ok(registry)
+    }
+
+    pub fn insert(&mut self, def: TileDef) -> Result<(), String> {
+        if self.defs.contains_key(&def.id) {
+            return Err(format!("duplicate tile id {}", def.id.0));
+        }
+        if let Some(symbol) = def.symbol {
+            if let Some(other) = self.by_symbol(symbol) {
+                return Err(format!(
+                    "tile {} reuses symbol '{}' of tile {}",
+                    def.id.0, symbol, other.0
+                ));
+            }
+        }
+        self.defs.insert(def.id, def);
+        Ok(())
+    }
+
+    pub fn get(&self, id: TileId) -> Option<&TileDef> {
+        self.defs.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TileDef> {
+        self.defs.values()
+    }
+
+    pub fn by_symbol(&self, symbol: char) -> Option<TileId> {
+        self.defs
+            .values()
+            .find(|def| def.symbol == Some(symbol))
+            .map(|def| def.id)
+    }
+
+    pub fn is_solid(&self, id: TileId) -> bool {
+        self.get(id).is_none_or(|def| def.solid)
+    }
+
+    pub fn blocks_sight(&self, id: TileId) -> bool {
+        self.get(id).is_none_or(|def| def.blocks_sight)
+    }
+
+    pub fn is_exit(&self, id: TileId) -> bool {
+        self.get(id).is_some_and(|def| def.exit)
+    }
+
+    pub fn is_door(&self, id: TileId) -> bool {
+        self.get(id).is_some_and(|def| def.door)
+    }
+
+    pub fn is_secret(&self, id: TileId) -> bool {
+        self.get(id).is_some_and(|def| def.secret)
+    }
+
+    pub fn segments(&self, id: TileId) -> &[Segment] {
+        self.get(id).map_or(&[], |def| &def.segments)
+    }
+
+    // height is the wall height of the tile in storeys,
+    // unknown tiles are one storey walls
+    pub fn height(&self, id: TileId) -> f64 {
+        self.get(id).map_or(1.0, |def| def.height)
+    }
+
+    // floor_height is how high the floor of the tile is raised in storeys
+    pub fn floor_height(&self, id: TileId) -> f64 {
+        self.get(id).map_or(0.0, |def| def.floor_height)
+    }
+
+    // top is the height in storeys rays see the tile rise to,
+    // the floor of raised tiles and the walls of the others
+    pub fn top(&self, id: TileId) -> f64 {
+        match self.floor_height(id) {
+            floor_height if floor_height > 0.0 => floor_height,
+            _ => self.height(id),
+        }
+    }
+
+    pub fn is_see_through(&self, id: TileId) -> bool {
+        self.get(id).is_some_and(|def| def.see_through)
+    }
+
+    pub fn is_portal(&self, id: TileId) -> bool {
+        self.get(id).is_some_and(|def| def.portal)
+    }
+
+    pub fn is_mirror(&self, id: TileId) -> bool {
+        self.get(id).is_some_and(|def| def.mirror)
+    }
+
+    pub fn tint(&self, id: TileId) -> Option<u32> {
+        self.get(id).and_then(|def| def.tint)
+    }
+
+    pub fn minimap_color(&self, id: TileId) -> u32 {
+        self.get(id)
+            .map_or(UNKNOWN_TILE_COLOR, |def| def.minimap_color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_tiles() {
+        let registry = TileRegistry::default();
+        assert!(!registry.is_solid(TileId::EMPTY));
+        assert!(registry.is_solid(TileId(1)));
+        assert_eq!(registry.by_symbol('.'), Some(TileId::EMPTY));
+        assert_eq!(registry.by_symbol('8'), Some(TileId(8)));
+        let door = registry.by_symbol('D').unwrap();
+        assert!(registry.is_door(door) && registry.is_solid(door));
+        assert!(!registry.is_door(TileId(1)));
+        let secret = registry.by_symbol('S').unwrap();
+        assert!(registry.is_secret(secret) && registry.is_solid(secret));
+        assert!(!registry.is_secret(TileId(1)));
+        let fence = registry.by_symbol('|').unwrap();
+        assert!(!registry.is_solid(fence) && !registry.blocks_sight(fence));
+        assert_eq!(registry.segments(fence).len(), 1);
+        assert!(registry.segments(TileId(1)).is_empty());
+        assert_eq!(registry.height(TileId(1)), 1.0);
+        assert_eq!(registry.height(registry.by_symbol('h').unwrap()), 0.5);
+        assert_eq!(registry.height(registry.by_symbol('T').unwrap()), 2.0);
+        let step = registry.by_symbol('b').unwrap();
+        assert!(!registry.is_solid(step) && !registry.blocks_sight(step));
+        assert_eq!(registry.floor_height(step), 0.25);
+        assert_eq!(registry.top(step), 0.25);
+        assert_eq!(registry.top(TileId(1)), 1.0);
+        assert_eq!(registry.floor_height(TileId(1)), 0.0);
+        let glass = registry.by_symbol('g').unwrap();
+        assert!(registry.is_see_through(glass) && registry.is_solid(glass));
+        let chainlink = registry.by_symbol(':').unwrap();
+        assert!(registry.is_see_through(chainlink) && !registry.is_solid(chainlink));
+        assert!(!registry.is_see_through(TileId(1)));
+        let portal = registry.by_symbol('O').unwrap();
+        assert!(registry.is_portal(portal) && registry.is_solid(portal));
+        assert!(!registry.is_portal(TileId(1)));
+        let mirror = registry.by_symbol('M').unwrap();
+        assert!(registry.is_mirror(mirror) && registry.is_solid(mirror));
+        assert!(registry.tint(mirror).is_some_and(|tint| tint >> 24 < 0xFF));
+        assert!(!registry.is_mirror(TileId(1)) && registry.tint(TileId(1)).is_none());
+    }
+
+    #[test]
+    fn test_unknown_tiles_are_solid() {
+        let registry = TileRegistry::default();
+        assert!(registry.get(TileId(400)).is_none());
+        assert!(registry.is_solid(TileId(400)));
+        assert!(registry.blocks_sight(TileId(400)));
+        assert_eq!(registry.minimap_color(TileId(400)), UNKNOWN_TILE_COLOR);
+    }
+
+    #[test]
+    fn test_from_toml() {
+        let registry = TileRegistry::from_toml(
+            r##"
+            [[tile]]
+            id = 12
+            name = "Hedge"
+            symbol = "h"
+            blocks_sight = false
+            texture = "hedge"
+            textures = { north = "hedge_flowers" }
+            floor = "grass"
+            minimap_color = "#00AA00"
+            "##,
+        )
+        .unwrap();
+
+        let hedge = registry.get(TileId(12)).unwrap();
+        assert!(hedge.solid);
+        assert!(!hedge.blocks_sight);
+        assert_eq!(hedge.minimap_color, 0xFF00AA00);
+        assert_eq!(hedge.textures.north.as_deref(), Some("hedge_flowers"));
+        assert_eq!(hedge.textures.west.as_deref(), Some("hedge"));
+        assert_eq!(hedge.floor.as_deref(), Some("grass"));
+        assert_eq!(hedge.ceiling, None);
+        assert_eq!(registry.by_symbol('h'), Some(TileId(12)));
+        assert!(registry.get(TileId::EMPTY).is_some());
+    }
+
+    #[test]
+    fn test_from_toml_errors() {
+        let duplicate = "[[tile]]\nid = 1\nname = \"a\"\n[[tile]]\nid = 1\nname = \"b\"\n";
+        assert!(TileRegistry::from_toml(duplicate).is_err());
+
+        let symbol = "[[tile]]\nid = 1\nname = \"a\"\nsymbol = \"x\"\n[[tile]]\nid = 2\nname = \"b\"\nsymbol = \"x\"\n";
+        assert!(TileRegistry::from_toml(symbol).is_err());
+
+        let color = "[[tile]]\nid = 1\nname = \"a\"\nminimap_color = \"red\"\n";
+        assert!(TileRegistry::from_toml(color).is_err());
+
+        let outside = "[[tile]]\nid = 1\nname = \"a\"\nsegments = [[0, 0, 2, 1]]\n";
+        assert!(TileRegistry::from_toml(outside)
+            .unwrap_err()
+            .contains("outside"));
+        for height in ["0", "nan", "inf", "-inf"] {
+            let source = format!("[[tile]]\nid = 1\nname = \"a\"\nheight = {}\n", height);
+            assert!(TileRegistry::from_toml(&source)
+                .unwrap_err()
+                .contains("height"));
+        }
+        for floor_height in ["-0.25", "0.5", "nan"] {
+            let source = format!(
+                "[[tile]]\nid = 1\nname = \"a\"\nsolid = false\nblocks_sight = false\nfloor_height = {}\n",
+                floor_height
+            );
+            assert!(TileRegistry::from_toml(&source)
+                .unwrap_err()
+                .contains("floor height"));
+        }
+        let raised_wall = "[[tile]]\nid = 1\nname = \"a\"\nfloor_height = 0.25\n";
+        assert!(TileRegistry::from_toml(raised_wall)
+            .unwrap_err()
+            .contains("open tiles"));
+        let point = "[[tile]]\nid = 1\nname = \"a\"\nsegments = [[0.5, 0.5, 0.5, 0.5]]\n";
+        assert!(TileRegistry::from_toml(point)
+            .unwrap_err()
+            .contains("no length"));
+    }
+}
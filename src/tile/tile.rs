@@ -0,0 +1,194 @@
+use crate::{utils::geometry::line_intersection, window::window::TILE_SIZE};
+
+// TileId identifies a tile definition in the registry,
+// the id 0 is reserved for empty space
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TileId(pub u16);
+
+impl TileId {
+    pub const EMPTY: TileId = TileId(0);
+}
+
+// Face is the side of a wall cell a ray hits,
+// named after the direction the side looks towards
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Face {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Face {
+    // from_hit finds the face a ray going in the given direction hits:
+    // crossing a vertical grid line to the right hits the west face of the cell
+    pub fn from_hit(is_vertical: bool, facing_right: bool, facing_down: bool) -> Self {
+        match (is_vertical, facing_right, facing_down) {
+            (true, true, _) => Face::West,
+            (true, false, _) => Face::East,
+            (false, _, true) => Face::North,
+            (false, _, false) => Face::South,
+        }
+    }
+}
+
+// FaceTextures holds the name of the wall texture used by each face
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FaceTextures {
+    pub north: Option<String>,
+    pub south: Option<String>,
+    pub east: Option<String>,
+    pub west: Option<String>,
+}
+
+impl FaceTextures {
+    pub fn get(&self, face: Face) -> Option<&str> {
+        match face {
+            Face::North => self.north.as_deref(),
+            Face::South => self.south.as_deref(),
+            Face::East => self.east.as_deref(),
+            Face::West => self.west.as_deref(),
+        }
+    }
+}
+
+// Segment is a wall standing inside a tile from x1, y1 to x2, y2,
+// given in fractions of the tile from its top left corner
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    pub x1: f64,
+    pub y1: f64,
+    pub x2: f64,
+    pub y2: f64,
+}
+
+// SegmentHit is where a ray meets a segment: its distance from the ray origin,
+// the point hit and how far along the segment that is, in world units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SegmentHit {
+    pub distance: f64,
+    pub x: f64,
+    pub y: f64,
+    pub along: f64,
+    pub is_vertical: bool,
+}
+
+impl Segment {
+    // world returns the ends of the segment in the cell x, y in world units
+    pub fn world(&self, x: usize, y: usize) -> ((f64, f64), (f64, f64)) {
+        let tile = TILE_SIZE as f64;
+        let (left, top) = (x as f64 * tile, y as f64 * tile);
+        (
+            (left + self.x1 * tile, top + self.y1 * tile),
+            (left + self.x2 * tile, top + self.y2 * tile),
+        )
+    }
+
+    // is_vertical tells whether the segment is closer to a vertical line,
+    // it is then shaded like the vertical walls
+    pub fn is_vertical(&self) -> bool {
+        (self.y2 - self.y1).abs() > (self.x2 - self.x1).abs()
+    }
+
+    // intersect finds where a ray from x, y along angle meets the segment
+    // placed in the cell cell_x, cell_y
+    pub fn intersect(
+        &self,
+        cell_x: usize,
+        cell_y: usize,
+        x: f64,
+        y: f64,
+        angle: f64,
+    ) -> Option<SegmentHit> {
+        let (a, b) = self.world(cell_x, cell_y);
+        let (sin, cos) = angle.sin_cos();
+        let (distance, along) = line_intersection((x, y), (cos, sin), a, b)?;
+        if distance < 0.0 {
+            return None;
+        }
+        Some(SegmentHit {
+            distance,
+            x: x + cos * distance,
+            y: y + sin * distance,
+            along: along * (b.0 - a.0).hypot(b.1 - a.1),
+            is_vertical: self.is_vertical(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileDef {
+    pub id: TileId,
+    pub name: String,
+    pub symbol: Option<char>,
+    pub solid: bool,
+    pub blocks_sight: bool,
+    // exit tiles end the level when the player reaches them
+    pub exit: bool,
+    // door tiles are thin panels across the middle of the cell that slide open
+    pub door: bool,
+    // secret tiles are walls that slide away when the player pushes them
+    pub secret: bool,
+    // segments are thin walls inside the tile, rays and the player
+    // pass through the rest of it
+    pub segments: Vec<Segment>,
+    // height of the walls of the tile in storeys of one tile
+    pub height: f64,
+    // floor_height raises the floor of open tiles in storeys, for steps and platforms
+    pub floor_height: f64,
+    // see through tiles are drawn with the clear texels of their textures
+    // showing what lies behind them, rays go on past them
+    pub see_through: bool,
+    // portal tiles joined by the level lead to another portal tile,
+    // a portal tile joined to no other is a plain wall
+    pub portal: bool,
+    // mirror tiles reflect rays off their faces, tinting what they show
+    // towards the colour of tint by its alpha
+    pub mirror: bool,
+    pub tint: Option<u32>,
+    pub textures: FaceTextures,
+    // floor and ceiling textures override the ones of the level
+    pub floor: Option<String>,
+    pub ceiling: Option<String>,
+    // sky tiles have no ceiling, the sky of the level is drawn over them
+    pub sky: bool,
+    pub minimap_color: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_face_from_hit() {
+        assert_eq!(Face::from_hit(true, true, false), Face::West);
+        assert_eq!(Face::from_hit(true, false, true), Face::East);
+        assert_eq!(Face::from_hit(false, true, true), Face::North);
+        assert_eq!(Face::from_hit(false, false, false), Face::South);
+    }
+
+    #[test]
+    fn test_segment_intersect() {
+        let tile = TILE_SIZE as f64;
+        let diagonal = Segment {
+            x1: 0.0,
+            y1: 1.0,
+            x2: 1.0,
+            y2: 0.0,
+        };
+        assert!(!diagonal.is_vertical());
+
+        // from the west the diagonal of the cell 2, 0 is met half way along
+        let hit = diagonal.intersect(2, 0, 0.0, 0.5 * tile, 0.0).unwrap();
+        assert!((hit.distance - 2.5 * tile).abs() < 1e-9);
+        assert!((hit.x - 2.5 * tile).abs() < 1e-9);
+        assert!((hit.along - tile / 2.0_f64.sqrt()).abs() < 1e-9);
+
+        // looking away or passing above the cell misses
+        assert_eq!(
+            diagonal.intersect(2, 0, 0.0, 0.5 * tile, std::f64::consts::PI),
+            None
+        );
+        assert_eq!(diagonal.intersect(2, 1, 0.0, 0.5 * tile, 0.0), None);
+    }
+}
@@ -0,0 +1,75 @@
+// parse_color reads a #RRGGBB or #AARRGGBB hex string
+// into the ARGB8888 layout used by the color buffer
+pub fn parse_color(s: &str) -> Result<u32, String> {
+    let hex = s
+        .strip_prefix('#')
+        .ok_or_else(|| format!("Invalid color {}, expected #RRGGBB", s))?;
+    let value = u32::from_str_radix(hex, 16).map_err(|_| format!("Invalid color {}", s))?;
+
+    match hex.len() {
+        6 => Ok(0xFF000000 | value),
+        8 => Ok(value),
+        _ => Err(format!("Invalid color {}, expected #RRGGBB", s)),
+    }
+}
+
+// shade_color scales the red, green and blue channels by factor
+// keeping the alpha channel untouched
+pub fn shade_color(color: u32, factor: f64) -> u32 {
+    let factor = factor.clamp(0.0, 1.0);
+    let scale = |shift: u32| ((((color >> shift) & 0xFF) as f64 * factor) as u32) << shift;
+    (color & 0xFF000000) | scale(16) | scale(8) | scale(0)
+}
+
+// is_transparent tells whether a texel is fully see through
+pub fn is_transparent(color: u32) -> bool {
+    color >> 24 == 0
+}
+
+// mix_color blends from color towards target by amount, 0 keeping color
+// and 1 giving target, the alpha channel of color is kept
+pub fn mix_color(color: u32, target: u32, amount: f64) -> u32 {
+    let amount = amount.clamp(0.0, 1.0);
+    let mix = |shift: u32| {
+        let from = ((color >> shift) & 0xFF) as f64;
+        let to = ((target >> shift) & 0xFF) as f64;
+        ((from + (to - from) * amount).round() as u32) << shift
+    };
+    (color & 0xFF000000) | mix(16) | mix(8) | mix(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#444444"), Ok(0xFF444444));
+        assert_eq!(parse_color("#80FF0000"), Ok(0x80FF0000));
+        assert!(parse_color("444444").is_err());
+        assert!(parse_color("#4444").is_err());
+        assert!(parse_color("#GG0000").is_err());
+    }
+
+    #[test]
+    fn test_mix_color() {
+        assert_eq!(mix_color(0xFF000000, 0xFFFFFFFF, 0.0), 0xFF000000);
+        assert_eq!(mix_color(0xFF000000, 0xFFFFFFFF, 1.0), 0xFFFFFFFF);
+        assert_eq!(mix_color(0x80204060, 0xFF000000, 0.5), 0x80102030);
+        assert_eq!(mix_color(0xFF204060, 0xFF000000, 7.0), 0xFF000000);
+    }
+
+    #[test]
+    fn test_is_transparent() {
+        assert!(is_transparent(0x00FF00FF));
+        assert!(!is_transparent(0x01000000));
+        assert!(!is_transparent(0xFF000000));
+    }
+
+    #[test]
+    fn test_shade_color() {
+        assert_eq!(shade_color(0xFFFFFFFF, 0.8), 0xFFCCCCCC);
+        assert_eq!(shade_color(0x80FF8040, 0.5), 0x807F4020);
+        assert_eq!(shade_color(0xFF123456, 1.0), 0xFF123456);
+    }
+}
@@ -19,6 +19,41 @@ pub fn is_angle_facing_right(angle: &mut f64) -> bool {
     *angle < 0.5 * PI || *angle > 1.5 * PI
 }
 
+// line_intersection intersects the line from origin along direction with the
+// segment from a to b. It returns how many directions along the line the
+// segment is met and where on the segment, from 0 at a to 1 at b.
+// Parallel lines and lines passing beside the segment give None.
+pub fn line_intersection(
+    origin: (f64, f64),
+    direction: (f64, f64),
+    a: (f64, f64),
+    b: (f64, f64),
+) -> Option<(f64, f64)> {
+    let edge = (b.0 - a.0, b.1 - a.1);
+    let denominator = direction.0 * edge.1 - direction.1 * edge.0;
+    if denominator.abs() < f64::EPSILON {
+        return None;
+    }
+    let to_a = (a.0 - origin.0, a.1 - origin.1);
+    let along_line = (to_a.0 * edge.1 - to_a.1 * edge.0) / denominator;
+    let along_segment = (to_a.0 * direction.1 - to_a.1 * direction.0) / denominator;
+    (0.0..=1.0)
+        .contains(&along_segment)
+        .then_some((along_line, along_segment))
+}
+
+// distance_to_segment is the distance from the point p to the nearest point
+// of the segment from a to b
+pub fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let edge = (b.0 - a.0, b.1 - a.1);
+    let length = edge.0 * edge.0 + edge.1 * edge.1;
+    let t = match length > 0.0 {
+        true => (((p.0 - a.0) * edge.0 + (p.1 - a.1) * edge.1) / length).clamp(0.0, 1.0),
+        false => 0.0,
+    };
+    distance_between_points(p.0, p.1, a.0 + edge.0 * t, a.1 + edge.1 * t)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,4 +112,32 @@ mod tests {
         let mut angle = PI;
         assert!(!is_angle_facing_right(&mut angle));
     }
+
+    #[test]
+    fn test_line_intersection() {
+        // a line going east meets the diagonal of the unit square half way
+        let (t, u) = line_intersection((-1.0, 0.5), (1.0, 0.0), (0.0, 0.0), (1.0, 1.0)).unwrap();
+        assert_eq!((t, u), (1.5, 0.5));
+
+        // behind the origin the line still meets it, with a negative distance
+        let (t, _) = line_intersection((3.0, 0.5), (1.0, 0.0), (0.0, 0.0), (1.0, 1.0)).unwrap();
+        assert_eq!(t, -2.5);
+
+        // parallel or passing beside
+        assert_eq!(
+            line_intersection((0.0, 0.5), (1.0, 0.0), (0.0, 0.0), (1.0, 0.0)),
+            None
+        );
+        assert_eq!(
+            line_intersection((0.0, 2.0), (1.0, 0.0), (0.0, 0.0), (1.0, 1.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_distance_to_segment() {
+        assert_eq!(distance_to_segment((0.5, 2.0), (0.0, 0.0), (1.0, 0.0)), 2.0);
+        assert_eq!(distance_to_segment((4.0, 4.0), (0.0, 0.0), (1.0, 0.0)), 5.0);
+        assert_eq!(distance_to_segment((3.0, 4.0), (0.0, 0.0), (0.0, 0.0)), 5.0);
+    }
 }
@@ -1 +1,3 @@
+pub mod color;
 pub mod geometry;
+pub mod time;
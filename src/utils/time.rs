@@ -0,0 +1,17 @@
+// format_time writes a duration in seconds as minutes and seconds, e.g. 1:05
+pub fn format_time(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(90.0), "1:30");
+        assert_eq!(format_time(5.9), "0:05");
+        assert_eq!(format_time(3600.0), "60:00");
+    }
+}
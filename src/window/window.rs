@@ -1,15 +1,81 @@
-use std::f64::consts::PI;
-
-use lazy_static::lazy_static;
+use std::{f64::consts::PI, str::FromStr};
 
 pub const MINIMAP_SCALING: f64 = 0.2;
 pub const TILE_SIZE: u32 = 64;
-pub const NUM_ROWS: u32 = 13;
-pub const NUM_COLS: u32 = 20;
 pub const FOV: f64 = 60.0 * (PI / 180.0);
-pub const WINDOW_WIDTH: u32 = NUM_COLS * TILE_SIZE;
-pub const WINDOW_HEIGHT: u32 = NUM_ROWS * TILE_SIZE;
-lazy_static! {
-    pub static ref DISTANCE_PROJ_PLANE: f64 = (WINDOW_WIDTH as f64 / 2.0) / (FOV / 2.0).tan();
+
+// Resolution is the size in pixels of the rendered image,
+// it is independent from the size of the world being rendered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for Resolution {
+    fn default() -> Self {
+        Resolution {
+            width: 1280,
+            height: 832,
+        }
+    }
+}
+
+impl Resolution {
+    pub fn new(width: u32, height: u32) -> Self {
+        Resolution { width, height }
+    }
+
+    // one ray is cast for every column of the output
+    pub fn num_rays(&self) -> u32 {
+        self.width
+    }
+
+    pub fn distance_proj_plane(&self) -> f64 {
+        (self.width as f64 / 2.0) / (FOV / 2.0).tan()
+    }
+}
+
+// Resolutions are written as WIDTHxHEIGHT, e.g. 640x400
+impl FromStr for Resolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s
+            .split_once(['x', 'X'])
+            .ok_or_else(|| format!("Invalid resolution {}, expected WIDTHxHEIGHT", s))?;
+        let width: u32 = width
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid resolution width {}", width))?;
+        let height: u32 = height
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid resolution height {}", height))?;
+        if width == 0 || height == 0 {
+            return Err(format!("Resolution {} must not be empty", s));
+        }
+        Ok(Resolution::new(width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolution_from_str() {
+        assert_eq!("320x200".parse(), Ok(Resolution::new(320, 200)));
+        assert_eq!("1920X1080".parse(), Ok(Resolution::new(1920, 1080)));
+        assert!("640".parse::<Resolution>().is_err());
+        assert!("0x200".parse::<Resolution>().is_err());
+        assert!("axb".parse::<Resolution>().is_err());
+    }
+
+    #[test]
+    fn test_distance_proj_plane_scales_with_width() {
+        let low = Resolution::new(320, 200).distance_proj_plane();
+        let high = Resolution::new(640, 400).distance_proj_plane();
+        assert!((high - 2.0 * low).abs() < 1e-9);
+    }
 }
-pub const NUM_RAYS: u32 = WINDOW_WIDTH;